@@ -1,7 +1,9 @@
+use jarvix::cassette::Cassette;
 use jarvix::{EnrichmentEngine, EnrichmentConfig};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ScoredRecord {
@@ -22,8 +24,11 @@ async fn main() -> anyhow::Result<()> {
     let config_content = fs::read_to_string(config_path)?;
     let config: EnrichmentConfig = toml::from_str(&config_content)?;
 
-    // Create enrichment engine
-    let engine = EnrichmentEngine::new(config)?;
+    // Create enrichment engine; JARVIX_RECORD / JARVIX_REPLAY point at a cassette directory
+    let record = std::env::var_os("JARVIX_RECORD").map(PathBuf::from);
+    let replay = std::env::var_os("JARVIX_REPLAY").map(PathBuf::from);
+    let cassette = Cassette::from_options(record.as_deref(), replay.as_deref())?;
+    let engine = EnrichmentEngine::new(config)?.with_cassette(Arc::new(cassette));
 
     // Example: Load scored records from JSONL file
     let scores_path = "data/scores/demo_001.jsonl";
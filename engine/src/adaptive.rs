@@ -0,0 +1,440 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Adaptive concurrency settings
+///
+/// When enabled, the number of in-flight requests starts at `min_concurrent`
+/// and is tuned AIMD-style between `min_concurrent` and the downloader's
+/// `max_concurrent`: it grows while latency and error rates stay healthy and
+/// is cut back multiplicatively when they degrade.
+#[derive(Debug, Clone)]
+pub struct AdaptiveConfig {
+    pub enabled: bool,
+    /// Lowest concurrency the controller will go down to
+    pub min_concurrent: usize,
+    /// Fewest completed requests between two adjustments
+    pub min_samples: usize,
+    /// Share of transient failures in a window that triggers a decrease
+    pub max_error_rate: f64,
+    /// Window latency above `baseline * latency_tolerance` triggers a decrease
+    pub latency_tolerance: f64,
+    /// Factor applied to the limit on a decrease
+    pub decrease_factor: f64,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_concurrent: 4,
+            min_samples: 8,
+            max_error_rate: 0.1,
+            latency_tolerance: 2.0,
+            decrease_factor: 0.5,
+        }
+    }
+}
+
+/// Outcome of one request, as seen by the controller
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub latency: Duration,
+    /// The request failed in a way that suggests overload (timeout, 429, 5xx, ...)
+    pub overloaded: bool,
+}
+
+/// Permits held over time
+#[derive(Debug)]
+struct InFlight {
+    current: usize,
+    peak: usize,
+    /// Permits held integrated over time, in permit-seconds
+    weighted: f64,
+    changed: Instant,
+}
+
+impl InFlight {
+    fn new(now: Instant) -> Self {
+        Self {
+            current: 0,
+            peak: 0,
+            weighted: 0.0,
+            changed: now,
+        }
+    }
+
+    /// Permit-seconds up to `now`
+    fn weighted_until(&self, now: Instant) -> f64 {
+        self.weighted + now.duration_since(self.changed).as_secs_f64() * self.current as f64
+    }
+
+    fn set(&mut self, current: usize) {
+        let now = Instant::now();
+        self.weighted = self.weighted_until(now);
+        self.changed = now;
+        self.current = current;
+        self.peak = self.peak.max(current);
+    }
+}
+
+/// Permit for one in-flight request; released on drop
+pub struct ConcurrencyPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    shrink: Arc<AtomicUsize>,
+    in_flight: Arc<Mutex<InFlight>>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        let Some(permit) = self.permit.take() else {
+            return;
+        };
+
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            let current = in_flight.current.saturating_sub(1);
+            in_flight.set(current);
+        }
+
+        // Retire the permit instead of returning it while the limit is shrinking
+        let retired = self
+            .shrink
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+            .is_ok();
+        if retired {
+            permit.forget();
+        }
+    }
+}
+
+/// Concurrency limit and actual usage over a run
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyReport {
+    /// Lowest limit
+    pub min: usize,
+    /// Highest limit
+    pub max: usize,
+    /// Time-weighted average limit
+    pub average: f64,
+    /// Limit at the end of the run
+    pub last: usize,
+    pub adjustments: usize,
+    /// Limit after each adjustment, as (seconds since start, limit)
+    pub timeline: Vec<(f64, usize)>,
+    /// Most requests in flight at once
+    pub peak_in_flight: usize,
+    /// Time-weighted average of requests in flight
+    pub average_in_flight: f64,
+}
+
+impl ConcurrencyReport {
+    /// Timeline formatted for logs, downsampled to at most `points` entries
+    pub fn timeline_summary(&self, points: usize) -> String {
+        let step = self.timeline.len().div_ceil(points.max(1)).max(1);
+        let mut sampled: Vec<&(f64, usize)> = self.timeline.iter().step_by(step).collect();
+        if let Some(last) = self.timeline.last() {
+            if sampled.last().is_some_and(|s| !std::ptr::eq(*s, last)) {
+                sampled.push(last);
+            }
+        }
+
+        sampled
+            .iter()
+            .map(|(secs, limit)| format!("{:.0}s:{}", secs, limit))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+struct ControllerState {
+    limit: usize,
+    /// Doubling until the first decrease, then additive increase
+    slow_start: bool,
+    samples: usize,
+    overloaded: usize,
+    latency_total: Duration,
+    baseline: Option<Duration>,
+    history: Vec<(Instant, usize)>,
+}
+
+/// Global concurrency limit for a downloader, optionally adjusted AIMD-style
+pub struct ConcurrencyController {
+    config: AdaptiveConfig,
+    max: usize,
+    min: usize,
+    semaphore: Arc<Semaphore>,
+    /// Permits to retire as they are released, after a decrease
+    shrink: Arc<AtomicUsize>,
+    started: Instant,
+    state: Mutex<ControllerState>,
+    in_flight: Arc<Mutex<InFlight>>,
+}
+
+impl ConcurrencyController {
+    pub fn new(max_concurrent: usize, config: AdaptiveConfig) -> Self {
+        let max = max_concurrent.max(1);
+        let min = config.min_concurrent.clamp(1, max);
+        let initial = if config.enabled { min } else { max };
+        let started = Instant::now();
+
+        Self {
+            config,
+            max,
+            min,
+            semaphore: Arc::new(Semaphore::new(initial)),
+            shrink: Arc::new(AtomicUsize::new(0)),
+            started,
+            state: Mutex::new(ControllerState {
+                limit: initial,
+                slow_start: true,
+                samples: 0,
+                overloaded: 0,
+                latency_total: Duration::ZERO,
+                baseline: None,
+                history: vec![(started, initial)],
+            }),
+            in_flight: Arc::new(Mutex::new(InFlight::new(started))),
+        }
+    }
+
+    /// Current concurrency limit
+    pub fn limit(&self) -> usize {
+        self.state.lock().expect("Controller state poisoned").limit
+    }
+
+    /// Wait for a free slot under the current limit
+    pub async fn acquire(&self) -> ConcurrencyPermit {
+        let permit = Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .expect("Concurrency semaphore closed");
+
+        let mut in_flight = self.in_flight.lock().expect("In-flight count poisoned");
+        let current = in_flight.current + 1;
+        in_flight.set(current);
+
+        ConcurrencyPermit {
+            permit: Some(permit),
+            shrink: Arc::clone(&self.shrink),
+            in_flight: Arc::clone(&self.in_flight),
+        }
+    }
+
+    /// Feed the outcome of a request; adjusts the limit once a window is complete
+    pub fn record(&self, sample: Sample) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut state = self.state.lock().expect("Controller state poisoned");
+        state.samples += 1;
+        state.latency_total += sample.latency;
+        if sample.overloaded {
+            state.overloaded += 1;
+        }
+
+        // Roughly one window per "round trip" of the current limit
+        let window = state.limit.max(self.config.min_samples).max(1);
+        if state.samples < window {
+            return;
+        }
+
+        let average = state.latency_total / state.samples as u32;
+        let error_rate = state.overloaded as f64 / state.samples as f64;
+        state.samples = 0;
+        state.overloaded = 0;
+        state.latency_total = Duration::ZERO;
+
+        let baseline = *state.baseline.get_or_insert(average);
+        let slow = average.as_secs_f64() > baseline.as_secs_f64() * self.config.latency_tolerance;
+
+        let next = if error_rate > self.config.max_error_rate || slow {
+            state.slow_start = false;
+            ((state.limit as f64 * self.config.decrease_factor) as usize).max(self.min)
+        } else if state.slow_start {
+            (state.limit * 2).min(self.max)
+        } else {
+            (state.limit + 1).min(self.max)
+        };
+
+        // Let the baseline follow latency upwards slowly so one fast window
+        // early in the run doesn't pin it forever
+        state.baseline = Some(average.min(baseline.mul_f64(1.1)));
+
+        if next != state.limit {
+            self.resize(state.limit, next);
+            state.limit = next;
+            state.history.push((Instant::now(), next));
+        }
+    }
+
+    fn resize(&self, current: usize, next: usize) {
+        if next > current {
+            let mut grow = next - current;
+            // Cancel pending retirements before adding new permits
+            while grow > 0
+                && self
+                    .shrink
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+                    .is_ok()
+            {
+                grow -= 1;
+            }
+            self.semaphore.add_permits(grow);
+        } else {
+            let mut shrink = current - next;
+            // Idle permits can be removed right away, busy ones on release
+            while shrink > 0 {
+                match Arc::clone(&self.semaphore).try_acquire_owned() {
+                    Ok(permit) => permit.forget(),
+                    Err(_) => break,
+                }
+                shrink -= 1;
+            }
+            self.shrink.fetch_add(shrink, Ordering::AcqRel);
+        }
+    }
+
+    /// Concurrency limits and usage so far
+    pub fn report(&self) -> ConcurrencyReport {
+        let state = self.state.lock().expect("Controller state poisoned");
+        let in_flight = self.in_flight.lock().expect("In-flight count poisoned");
+        let now = Instant::now();
+
+        let mut weighted = 0.0;
+        for (i, (at, limit)) in state.history.iter().enumerate() {
+            let until = state.history.get(i + 1).map_or(now, |(next, _)| *next);
+            weighted += until.duration_since(*at).as_secs_f64() * *limit as f64;
+        }
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+
+        ConcurrencyReport {
+            min: state.history.iter().map(|(_, l)| *l).min().unwrap_or_default(),
+            max: state.history.iter().map(|(_, l)| *l).max().unwrap_or_default(),
+            average: if elapsed > 0.0 { weighted / elapsed } else { state.limit as f64 },
+            last: state.limit,
+            adjustments: state.history.len() - 1,
+            timeline: state
+                .history
+                .iter()
+                .map(|(at, limit)| (at.duration_since(self.started).as_secs_f64(), *limit))
+                .collect(),
+            peak_in_flight: in_flight.peak,
+            average_in_flight: if elapsed > 0.0 {
+                in_flight.weighted_until(now) / elapsed
+            } else {
+                in_flight.current as f64
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> Sample {
+        Sample {
+            latency: Duration::from_millis(50),
+            overloaded: false,
+        }
+    }
+
+    fn controller(max: usize) -> ConcurrencyController {
+        ConcurrencyController::new(
+            max,
+            AdaptiveConfig {
+                enabled: true,
+                min_concurrent: 2,
+                min_samples: 4,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_grows_while_healthy_and_backs_off_on_errors() {
+        let controller = controller(16);
+        assert_eq!(controller.limit(), 2);
+
+        // Slow start: 2 -> 4 -> 8 -> 16
+        for _ in 0..(4 + 4 + 8) {
+            controller.record(healthy());
+        }
+        assert_eq!(controller.limit(), 16);
+
+        for _ in 0..16 {
+            controller.record(Sample {
+                latency: Duration::from_millis(50),
+                overloaded: true,
+            });
+        }
+        assert_eq!(controller.limit(), 8);
+
+        // Additive increase after the first decrease
+        for _ in 0..8 {
+            controller.record(healthy());
+        }
+        assert_eq!(controller.limit(), 9);
+
+        let report = controller.report();
+        assert_eq!(report.min, 2);
+        assert_eq!(report.max, 16);
+        assert_eq!(report.last, 9);
+        assert_eq!(report.adjustments, 5);
+    }
+
+    #[test]
+    fn test_latency_spike_decreases_limit() {
+        let controller = controller(16);
+        for _ in 0..4 {
+            controller.record(healthy());
+        }
+        assert_eq!(controller.limit(), 4);
+
+        for _ in 0..4 {
+            controller.record(Sample {
+                latency: Duration::from_millis(500),
+                overloaded: false,
+            });
+        }
+        assert_eq!(controller.limit(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shrinking_retires_busy_permits() {
+        let controller = controller(8);
+        for _ in 0..4 {
+            controller.record(healthy());
+        }
+        assert_eq!(controller.limit(), 4);
+
+        let held: Vec<_> = futures::future::join_all((0..4).map(|_| controller.acquire())).await;
+        for _ in 0..4 {
+            controller.record(Sample {
+                latency: Duration::from_millis(50),
+                overloaded: true,
+            });
+        }
+        assert_eq!(controller.limit(), 2);
+
+        drop(held);
+        assert_eq!(controller.semaphore.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reports_permits_in_flight_next_to_limit() {
+        let controller = ConcurrencyController::new(16, AdaptiveConfig::default());
+
+        let held: Vec<_> = futures::future::join_all((0..3).map(|_| controller.acquire())).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        let _one = controller.acquire().await;
+
+        let report = controller.report();
+        assert_eq!(report.last, 16);
+        assert_eq!(report.peak_in_flight, 3);
+        assert!(report.average_in_flight > 0.0 && report.average_in_flight <= 3.0);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::parallel::DownloadResult;
+
+/// Per-URL latency distribution, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencySummary {
+    pub fn from_durations(durations_ms: &[u64]) -> Self {
+        if durations_ms.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = durations_ms.to_vec();
+        sorted.sort_unstable();
+
+        Self {
+            mean_ms: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+            p50_ms: percentile(&sorted, 50.0),
+            p90_ms: percentile(&sorted, 90.0),
+            p99_ms: percentile(&sorted, 99.0),
+            max_ms: *sorted.last().unwrap_or(&0) as f64,
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1] as f64
+}
+
+/// Machine-readable result of a `jarvix benchmark` run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub generated_at: DateTime<Utc>,
+    /// What was benchmarked, e.g. "synthetic latency=fixed:10 ..." or the target URL
+    pub workload: String,
+    pub urls: usize,
+    pub concurrent: usize,
+    pub successful: usize,
+    pub success_rate: f64,
+    pub duration_secs: f64,
+    pub urls_per_second: f64,
+    pub bytes_total: u64,
+    pub bytes_per_second: f64,
+    /// Per-URL latency including retries and redirects
+    pub latency: LatencySummary,
+    /// Failed URLs per failure class ("timeout", "server_error", ...)
+    pub errors: BTreeMap<String, usize>,
+    /// Peak resident memory of the process (None where it can't be measured)
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl BenchmarkReport {
+    pub fn new(
+        results: &[DownloadResult],
+        duration: Duration,
+        concurrent: usize,
+        workload: String,
+    ) -> Self {
+        let secs = duration.as_secs_f64().max(f64::EPSILON);
+        let successful = results.iter().filter(|r| r.success).count();
+        let bytes_total: u64 = results.iter().filter_map(|r| r.content_length).sum();
+        let durations: Vec<u64> = results.iter().map(|r| r.duration_ms).collect();
+
+        let mut errors = BTreeMap::new();
+        for result in results.iter().filter(|r| !r.success) {
+            let kind = result.error_kind.map_or("other", |kind| kind.as_str());
+            *errors.entry(kind.to_string()).or_insert(0) += 1;
+        }
+
+        Self {
+            generated_at: Utc::now(),
+            workload,
+            urls: results.len(),
+            concurrent,
+            successful,
+            success_rate: successful as f64 / results.len().max(1) as f64,
+            duration_secs: duration.as_secs_f64(),
+            urls_per_second: results.len() as f64 / secs,
+            bytes_total,
+            bytes_per_second: bytes_total as f64 / secs,
+            latency: LatencySummary::from_durations(&durations),
+            errors,
+            peak_memory_bytes: peak_memory_bytes(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create report directory")?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write benchmark report {:?}", path))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read benchmark report {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid benchmark report {:?}", path))
+    }
+}
+
+/// Pass/fail thresholds for a benchmark run (None = not checked)
+#[derive(Debug, Clone, Default)]
+pub struct Targets {
+    pub max_p50_ms: Option<f64>,
+    pub max_p99_ms: Option<f64>,
+    pub min_urls_per_second: Option<f64>,
+    pub min_success_rate: Option<f64>,
+}
+
+impl Targets {
+    /// Human-readable description of every target the report misses
+    pub fn check(&self, report: &BenchmarkReport) -> Vec<String> {
+        let mut missed = Vec::new();
+        if let Some(max) = self.max_p50_ms.filter(|max| report.latency.p50_ms > *max) {
+            missed.push(format!("p50 {:.1}ms > {:.1}ms", report.latency.p50_ms, max));
+        }
+        if let Some(max) = self.max_p99_ms.filter(|max| report.latency.p99_ms > *max) {
+            missed.push(format!("p99 {:.1}ms > {:.1}ms", report.latency.p99_ms, max));
+        }
+        if let Some(min) = self.min_urls_per_second.filter(|min| report.urls_per_second < *min) {
+            missed.push(format!("throughput {:.1} URLs/s < {:.1}", report.urls_per_second, min));
+        }
+        if let Some(min) = self.min_success_rate.filter(|min| report.success_rate < *min) {
+            missed.push(format!(
+                "success rate {:.1}% < {:.1}%",
+                report.success_rate * 100.0,
+                min * 100.0
+            ));
+        }
+        missed
+    }
+}
+
+/// A metric that got worse than the baseline by more than the tolerance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+/// Compare a run against a baseline report
+///
+/// `tolerance` is the allowed relative change, e.g. 0.1 lets latency grow
+/// and throughput drop by up to 10% before it counts as a regression.
+pub fn compare(
+    current: &BenchmarkReport,
+    baseline: &BenchmarkReport,
+    tolerance: f64,
+) -> Vec<Regression> {
+    // (metric, baseline, current, higher is better)
+    let mut metrics = vec![
+        ("latency.p50_ms", baseline.latency.p50_ms, current.latency.p50_ms, false),
+        ("latency.p90_ms", baseline.latency.p90_ms, current.latency.p90_ms, false),
+        ("latency.p99_ms", baseline.latency.p99_ms, current.latency.p99_ms, false),
+        ("urls_per_second", baseline.urls_per_second, current.urls_per_second, true),
+        ("bytes_per_second", baseline.bytes_per_second, current.bytes_per_second, true),
+        ("success_rate", baseline.success_rate, current.success_rate, true),
+    ];
+    if let (Some(base), Some(now)) = (baseline.peak_memory_bytes, current.peak_memory_bytes) {
+        metrics.push(("peak_memory_bytes", base as f64, now as f64, false));
+    }
+
+    metrics
+        .into_iter()
+        .filter(|&(_, base, now, higher_is_better)| {
+            if higher_is_better {
+                now < base * (1.0 - tolerance)
+            } else {
+                now > base * (1.0 + tolerance)
+            }
+        })
+        .map(|(metric, baseline, current, _)| Regression {
+            metric,
+            baseline,
+            current,
+        })
+        .collect()
+}
+
+/// Peak resident set size of this process (VmHWM), Linux only
+pub fn peak_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::FailureKind;
+
+    fn result(duration_ms: u64, error_kind: Option<FailureKind>) -> DownloadResult {
+        DownloadResult {
+            success: error_kind.is_none(),
+            error_kind,
+            duration_ms,
+            content_length: error_kind.is_none().then_some(1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_report_percentiles_and_errors() {
+        let mut results: Vec<DownloadResult> = (1..=98).map(|ms| result(ms, None)).collect();
+        results.push(result(500, Some(FailureKind::Timeout)));
+        results.push(result(900, Some(FailureKind::ServerError)));
+
+        let report =
+            BenchmarkReport::new(&results, Duration::from_secs(2), 10, "test".to_string());
+        assert_eq!(report.latency.p50_ms, 50.0);
+        assert_eq!(report.latency.p90_ms, 90.0);
+        assert_eq!(report.latency.p99_ms, 500.0);
+        assert_eq!(report.latency.max_ms, 900.0);
+        assert_eq!(report.errors["timeout"], 1);
+        assert_eq!(report.errors["server_error"], 1);
+        assert_eq!(report.bytes_per_second, 49_000.0);
+
+        let targets = Targets {
+            max_p99_ms: Some(100.0),
+            min_success_rate: Some(0.95),
+            ..Default::default()
+        };
+        assert_eq!(targets.check(&report).len(), 1);
+    }
+
+    #[test]
+    fn test_compare_flags_regressions_beyond_tolerance() {
+        let results: Vec<DownloadResult> = (1..=100).map(|ms| result(ms, None)).collect();
+        let baseline =
+            BenchmarkReport::new(&results, Duration::from_secs(1), 10, "test".to_string());
+
+        let mut current = baseline.clone();
+        current.latency.p99_ms *= 1.05;
+        current.urls_per_second *= 0.5;
+        let regressions = compare(&current, &baseline, 0.1);
+        let metrics: Vec<&str> = regressions.iter().map(|r| r.metric).collect();
+        assert_eq!(metrics, vec!["urls_per_second"]);
+
+        assert!(compare(&baseline, &baseline, 0.0).is_empty());
+    }
+}
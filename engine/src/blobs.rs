@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info};
+
+use crate::parallel::DownloadResult;
+use crate::storage::{ParquetStorage, ReadOptions};
+
+const BLOB_ZSTD_LEVEL: i32 = 3;
+
+/// Content-addressed store of page bodies
+///
+/// Each distinct body is kept once as a zstd-compressed file named by the
+/// SHA-256 of its text (`<root>/ab/abcdef....zst`), so pages that did not change between
+/// runs cost no extra space. Raw run files then only hold the hash, in
+/// `content_blob`.
+#[derive(Debug)]
+pub struct BlobStore {
+    root: PathBuf,
+    written: AtomicUsize,
+    deduplicated: AtomicUsize,
+}
+
+impl BlobStore {
+    /// Open (creating if needed) a blob store directory
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create blob store {:?}", root))?;
+        Ok(Self {
+            root,
+            written: AtomicUsize::new(0),
+            deduplicated: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// File holding the blob with `hash`
+    pub fn path(&self, hash: &str) -> Result<PathBuf> {
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("Invalid blob hash '{}'", hash);
+        }
+        let hash = hash.to_ascii_lowercase();
+        Ok(self.root.join(&hash[..2]).join(format!("{}.zst", hash)))
+    }
+
+    pub fn contains(&self, hash: &str) -> Result<bool> {
+        Ok(self.path(hash)?.exists())
+    }
+
+    /// Store `content` under the SHA-256 of its text; returns the hash
+    pub fn put(&self, content: &str) -> Result<String> {
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let path = self.path(&hash)?;
+        // Mark a stored blob as just used, so gc's min_age keeps it until the
+        // run reusing it commits; one gc just removed is written again below
+        let touched = File::options()
+            .append(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        match touched {
+            Ok(()) => {
+                self.deduplicated.fetch_add(1, Ordering::Relaxed);
+                return Ok(hash);
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to touch blob {}", hash)),
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create blob directory")?;
+        }
+        let compressed = zstd::encode_all(content.as_bytes(), BLOB_ZSTD_LEVEL)
+            .context("Failed to compress blob")?;
+
+        // Write then rename, so a crash never leaves a truncated blob behind
+        let tmp = path.with_extension("zst.tmp");
+        std::fs::write(&tmp, compressed).with_context(|| format!("Failed to write {:?}", tmp))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("Failed to store blob {}", hash))?;
+
+        self.written.fetch_add(1, Ordering::Relaxed);
+        Ok(hash)
+    }
+
+    /// Content stored under `hash`, if any; fails if the blob does not match it
+    pub fn get(&self, hash: &str) -> Result<Option<String>> {
+        let path = self.path(hash)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let compressed = std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let bytes = zstd::decode_all(compressed.as_slice())
+            .with_context(|| format!("Corrupt blob {}", hash))?;
+        if !format!("{:x}", Sha256::digest(&bytes)).eq_ignore_ascii_case(hash) {
+            bail!("Blob {} does not match its hash", hash);
+        }
+        String::from_utf8(bytes)
+            .map(Some)
+            .with_context(|| format!("Blob {} is not UTF-8", hash))
+    }
+
+    /// Move the body of `result` into the store, leaving its hash in `content_blob`
+    ///
+    /// The key is the hash of the decoded text, not `body_sha256`: bodies that
+    /// decode to the same text share a blob.
+    pub fn externalize(&self, result: &mut DownloadResult) -> Result<()> {
+        let Some(content) = result.content.take() else {
+            return Ok(());
+        };
+        result.content_blob = Some(self.put(&content)?);
+        Ok(())
+    }
+
+    /// Load the body of a result read back from a raw file into `content`
+    pub fn resolve(&self, result: &mut DownloadResult) -> Result<()> {
+        if result.content.is_some() {
+            return Ok(());
+        }
+        if let Some(hash) = &result.content_blob {
+            result.content = Some(
+                self.get(hash)?
+                    .with_context(|| format!("Blob {} of {} is missing", hash, result.url))?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Blobs written and bodies found already stored since the store was opened
+    pub fn stats(&self) -> (usize, usize) {
+        (
+            self.written.load(Ordering::Relaxed),
+            self.deduplicated.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Hashes and files of every stored blob
+    fn blobs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut blobs = Vec::new();
+        for shard in std::fs::read_dir(&self.root).context("Failed to list blob store")? {
+            let shard = shard?.path();
+            if !shard.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&shard).context("Failed to list blob store")? {
+                let path = entry?.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if let Some(hash) = name.strip_suffix(".zst") {
+                    blobs.push((hash.to_string(), path.clone()));
+                }
+            }
+        }
+        Ok(blobs)
+    }
+
+    /// Delete blobs no raw file references
+    ///
+    /// Blobs younger than `min_age` are kept, so bodies of a run that is still
+    /// being collected survive.
+    pub fn gc(
+        &self,
+        referenced: &HashSet<String>,
+        min_age: Duration,
+        dry_run: bool,
+    ) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        let now = SystemTime::now();
+
+        for (hash, path) in self.blobs()? {
+            let metadata = std::fs::metadata(&path)?;
+            let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+            if referenced.contains(&hash) || age < min_age {
+                report.kept += 1;
+                continue;
+            }
+
+            debug!("Dropping unreferenced blob {}", hash);
+            if !dry_run {
+                std::fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+            }
+            report.removed += 1;
+            report.bytes_freed += metadata.len();
+        }
+
+        Ok(report)
+    }
+}
+
+/// Outcome of a blob store garbage collection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GcReport {
+    pub kept: usize,
+    pub removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Blob hashes referenced by the raw Parquet files under `dir` (recursively,
+/// including partitioned datasets and parts of unfinished runs)
+pub fn referenced_blobs(storage: &ParquetStorage, dir: &Path) -> Result<HashSet<String>> {
+    let options = ReadOptions {
+        columns: Some(vec!["content_blob".to_string()]),
+        ..Default::default()
+    };
+
+    let mut referenced = HashSet::new();
+    let mut files = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if !dir.exists() {
+            continue;
+        }
+        let entries = std::fs::read_dir(&dir).with_context(|| format!("Failed to list {:?}", dir))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "parquet") {
+                files += 1;
+                for batch in storage.open_reader::<DownloadResult, _>(&path, &options)? {
+                    referenced.extend(batch?.into_iter().filter_map(|r| r.content_blob));
+                }
+            }
+        }
+    }
+
+    info!("{} blobs referenced by {} raw files under {:?}", referenced.len(), files, dir);
+    Ok(referenced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_externalize_dedup_and_gc() {
+        let root = std::env::temp_dir().join("jarvix_test_blobs");
+        std::fs::remove_dir_all(&root).ok();
+        let store = BlobStore::open(root.join("blobs")).unwrap();
+        let storage = ParquetStorage::new();
+
+        // Raw bytes differ per fetch (say, another charset), the decoded text does not
+        let page = |url: &str, body: &str| DownloadResult {
+            url: url.to_string(),
+            success: true,
+            content: Some(body.to_string()),
+            body_sha256: Some(format!("{:x}", Sha256::digest(format!("{}{}", url, body)))),
+            ..Default::default()
+        };
+        let mut week_1 = [
+            page("https://a.test/", "<html>a</html>"),
+            page("https://b.test/", "<html>b</html>"),
+        ];
+        let mut week_2 = [
+            page("https://a.test/", "<html>a</html>"),
+            page("https://b.test/", "<html>b v2</html>"),
+        ];
+        for result in week_1.iter_mut().chain(week_2.iter_mut()) {
+            store.externalize(result).unwrap();
+            assert!(result.content.is_none());
+        }
+        assert_eq!(store.stats(), (3, 1));
+        assert_eq!(week_1[0].content_blob, week_2[0].content_blob);
+        assert_ne!(week_1[0].content_blob, week_1[0].body_sha256);
+
+        storage.save_results(&week_2, root.join("raw/week_2.parquet")).unwrap();
+        let mut read = storage
+            .read_results(root.join("raw/week_2.parquet"), &ReadOptions::default())
+            .unwrap();
+        store.resolve(&mut read[1]).unwrap();
+        assert_eq!(read[1].content.as_deref(), Some("<html>b v2</html>"));
+
+        // Week 1 was never saved, so its old version of b is garbage
+        let referenced = referenced_blobs(&storage, &root.join("raw")).unwrap();
+        assert_eq!(referenced.len(), 2);
+        let recent = store.gc(&referenced, Duration::from_secs(3600), false).unwrap();
+        assert_eq!(recent.removed, 0);
+        let report = store.gc(&referenced, Duration::ZERO, false).unwrap();
+        assert_eq!((report.kept, report.removed), (2, 1));
+        assert!(!store.contains(week_1[1].content_blob.as_deref().unwrap()).unwrap());
+        assert!(store.path("../../etc/passwd").is_err());
+
+        // A blob whose content does not match its name is rejected
+        let hash = read[0].content_blob.clone().unwrap();
+        let tampered = zstd::encode_all("<html>evil</html>".as_bytes(), BLOB_ZSTD_LEVEL).unwrap();
+        std::fs::write(store.path(&hash).unwrap(), tampered).unwrap();
+        assert!(store.get(&hash).is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_gc_keeps_old_blob_reused_by_a_run_in_progress() {
+        let root = std::env::temp_dir().join("jarvix_test_blobs_reuse");
+        std::fs::remove_dir_all(&root).ok();
+        let store = BlobStore::open(&root).unwrap();
+
+        let hash = store.put("<html>a</html>").unwrap();
+        let day_ago = SystemTime::now() - Duration::from_secs(86_400);
+        File::options()
+            .append(true)
+            .open(store.path(&hash).unwrap())
+            .unwrap()
+            .set_modified(day_ago)
+            .unwrap();
+
+        // A new run stores the same body before its part file references it
+        assert_eq!(store.put("<html>a</html>").unwrap(), hash);
+        let report = store.gc(&HashSet::new(), Duration::from_secs(3600), false).unwrap();
+        assert_eq!((report.kept, report.removed), (1, 0));
+        assert!(store.contains(&hash).unwrap());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
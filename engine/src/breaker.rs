@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Circuit breaker settings applied per host
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Consecutive transient failures that open the breaker (0 disables it)
+    pub failure_threshold: u32,
+    /// Time the breaker stays open before a probe request is let through, in seconds
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Closed { consecutive_failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probe_in_flight: bool },
+}
+
+/// State change worth reporting as an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Breaker tripped after this many consecutive failures
+    Opened { failures: u32 },
+    /// Probe failed while half-open; breaker is open again
+    Reopened,
+    /// Probe succeeded; host is healthy again
+    Closed,
+}
+
+/// Per-host circuit breakers for one downloader
+pub struct CircuitBreakers {
+    config: BreakerConfig,
+    hosts: Mutex<HashMap<String, State>>,
+}
+
+impl CircuitBreakers {
+    /// Create a new set of breakers
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.failure_threshold > 0
+    }
+
+    fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.config.cooldown_secs)
+    }
+
+    /// Whether requests to `host` are currently being short-circuited
+    pub fn is_open(&self, host: &str) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+
+        let hosts = self.hosts.lock().expect("Breaker map poisoned");
+        match hosts.get(host) {
+            Some(State::Open { until }) => Instant::now() < *until,
+            Some(State::HalfOpen { probe_in_flight }) => *probe_in_flight,
+            _ => false,
+        }
+    }
+
+    /// Ask to send a request to `host`; false means fail fast
+    ///
+    /// Once the cooldown has passed a single probe request is admitted.
+    pub fn try_admit(&self, host: &str) -> bool {
+        if !self.enabled() {
+            return true;
+        }
+
+        let mut hosts = self.hosts.lock().expect("Breaker map poisoned");
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(State::Closed { consecutive_failures: 0 });
+
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen { probe_in_flight: true };
+                true
+            }
+            State::Open { .. } => false,
+            State::HalfOpen { probe_in_flight } => {
+                if probe_in_flight {
+                    false
+                } else {
+                    *state = State::HalfOpen { probe_in_flight: true };
+                    true
+                }
+            }
+        }
+    }
+
+    /// Record a response that shows the host is alive
+    pub fn record_success(&self, host: &str) -> Option<Transition> {
+        if !self.enabled() {
+            return None;
+        }
+
+        let mut hosts = self.hosts.lock().expect("Breaker map poisoned");
+        let previous = hosts.insert(
+            host.to_string(),
+            State::Closed { consecutive_failures: 0 },
+        );
+
+        match previous {
+            Some(State::HalfOpen { .. }) | Some(State::Open { .. }) => Some(Transition::Closed),
+            _ => None,
+        }
+    }
+
+    /// Record an outcome that says nothing about the host's health (e.g. a
+    /// DNS error or a redirect loop), letting another probe through if this
+    /// request was the half-open probe
+    pub fn release_probe(&self, host: &str) {
+        if !self.enabled() {
+            return;
+        }
+
+        let mut hosts = self.hosts.lock().expect("Breaker map poisoned");
+        if let Some(state @ State::HalfOpen { probe_in_flight: true }) = hosts.get_mut(host) {
+            *state = State::HalfOpen { probe_in_flight: false };
+        }
+    }
+
+    /// Record a transient failure against `host`
+    pub fn record_failure(&self, host: &str) -> Option<Transition> {
+        if !self.enabled() {
+            return None;
+        }
+
+        let open = State::Open {
+            until: Instant::now() + self.cooldown(),
+        };
+
+        let mut hosts = self.hosts.lock().expect("Breaker map poisoned");
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(State::Closed { consecutive_failures: 0 });
+
+        match *state {
+            State::Closed { consecutive_failures } => {
+                let failures = consecutive_failures + 1;
+                if failures >= self.config.failure_threshold {
+                    *state = open;
+                    Some(Transition::Opened { failures })
+                } else {
+                    *state = State::Closed { consecutive_failures: failures };
+                    None
+                }
+            }
+            State::HalfOpen { .. } => {
+                *state = open;
+                Some(Transition::Reopened)
+            }
+            // Late failures from requests started before the trip
+            State::Open { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let breakers = CircuitBreakers::new(BreakerConfig {
+            failure_threshold: 3,
+            cooldown_secs: 60,
+        });
+
+        assert_eq!(breakers.record_failure("dead.com"), None);
+        assert_eq!(breakers.record_failure("dead.com"), None);
+        assert_eq!(
+            breakers.record_failure("dead.com"),
+            Some(Transition::Opened { failures: 3 })
+        );
+        assert!(breakers.is_open("dead.com"));
+        assert!(!breakers.try_admit("dead.com"));
+        assert!(breakers.try_admit("alive.com"));
+    }
+
+    #[test]
+    fn test_breaker_half_opens_after_cooldown() {
+        let breakers = CircuitBreakers::new(BreakerConfig {
+            failure_threshold: 1,
+            cooldown_secs: 0,
+        });
+
+        breakers.record_failure("flaky.com");
+
+        // Only one probe at a time
+        assert!(breakers.try_admit("flaky.com"));
+        assert!(!breakers.try_admit("flaky.com"));
+
+        assert_eq!(breakers.record_success("flaky.com"), Some(Transition::Closed));
+        assert!(!breakers.is_open("flaky.com"));
+    }
+
+    #[test]
+    fn test_released_probe_admits_another() {
+        let breakers = CircuitBreakers::new(BreakerConfig {
+            failure_threshold: 1,
+            cooldown_secs: 0,
+        });
+
+        breakers.record_failure("flaky.com");
+        assert!(breakers.try_admit("flaky.com"));
+        assert!(breakers.is_open("flaky.com"));
+
+        breakers.release_probe("flaky.com");
+        assert!(!breakers.is_open("flaky.com"));
+        assert!(breakers.try_admit("flaky.com"));
+        assert!(!breakers.try_admit("flaky.com"));
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let breakers = CircuitBreakers::new(BreakerConfig {
+            failure_threshold: 2,
+            cooldown_secs: 60,
+        });
+
+        breakers.record_failure("a.com");
+        breakers.record_success("a.com");
+        assert_eq!(breakers.record_failure("a.com"), None);
+    }
+}
@@ -0,0 +1,473 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, info};
+
+use crate::retry::FailureKind;
+
+const INDEX_FILE: &str = "exchanges.jsonl";
+const BODIES_DIR: &str = "bodies";
+
+/// One recorded request and its response (or transport error)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    url: String,
+    recorded_at: DateTime<Utc>,
+    status: Option<u16>,
+    version: Option<String>,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    /// Body file under `bodies/`, named by its SHA-256
+    body_sha256: Option<String>,
+    error: Option<RecordedError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedError {
+    kind: String,
+    message: String,
+}
+
+/// Error from sending a request through a cassette
+#[derive(Debug)]
+pub enum HttpError {
+    /// Live request failed
+    Network(reqwest::Error),
+    /// Replayed transport failure recorded in an earlier run
+    Replayed { kind: FailureKind, message: String },
+    /// Replay mode and nothing was recorded for this request
+    NotRecorded { method: String, url: String },
+    /// Record mode and the body went past the size limit
+    BodyTooLarge { size: u64, limit: u64 },
+    /// The cassette itself could not be read or written
+    Cassette(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Network(e) => write!(f, "{}", e),
+            HttpError::Replayed { message, .. } => write!(f, "{} (replayed)", message),
+            HttpError::NotRecorded { method, url } => {
+                write!(f, "No recorded exchange for {} {}", method, url)
+            }
+            HttpError::BodyTooLarge { size, limit } => {
+                write!(f, "Body of {} bytes exceeds limit of {} bytes", size, limit)
+            }
+            HttpError::Cassette(message) => write!(f, "Cassette error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+enum Mode {
+    Live,
+    Record {
+        dir: PathBuf,
+        index: Mutex<File>,
+    },
+    Replay {
+        dir: PathBuf,
+        /// Exchanges per "METHOD url", with the position of the next one to serve
+        exchanges: Mutex<HashMap<String, (Vec<Exchange>, usize)>>,
+    },
+}
+
+/// Record/replay layer in front of reqwest
+///
+/// In record mode every exchange is appended to `<dir>/exchanges.jsonl` with
+/// bodies stored once per SHA-256 under `<dir>/bodies/`. In replay mode
+/// requests are answered from that directory without touching the network;
+/// repeated requests for a URL get its recorded responses in order, and the
+/// last one once they run out.
+pub struct Cassette {
+    mode: Mode,
+}
+
+impl fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.mode {
+            Mode::Live => write!(f, "Cassette(live)"),
+            Mode::Record { dir, .. } => write!(f, "Cassette(record {:?})", dir),
+            Mode::Replay { dir, .. } => write!(f, "Cassette(replay {:?})", dir),
+        }
+    }
+}
+
+impl Default for Cassette {
+    fn default() -> Self {
+        Self::live()
+    }
+}
+
+impl Cassette {
+    /// Send requests to the network without recording
+    pub fn live() -> Self {
+        Self { mode: Mode::Live }
+    }
+
+    /// Record exchanges into `dir`, appending to any existing recording
+    pub fn record(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir.join(BODIES_DIR))
+            .with_context(|| format!("Failed to create cassette directory {:?}", dir))?;
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(INDEX_FILE))
+            .context("Failed to open cassette index")?;
+
+        info!("Recording HTTP exchanges to {:?}", dir);
+        Ok(Self {
+            mode: Mode::Record {
+                dir: dir.to_path_buf(),
+                index: Mutex::new(index),
+            },
+        })
+    }
+
+    /// Serve exchanges previously recorded in `dir`
+    pub fn replay(dir: &Path) -> Result<Self> {
+        let file = File::open(dir.join(INDEX_FILE))
+            .with_context(|| format!("No cassette found in {:?}", dir))?;
+
+        let mut exchanges: HashMap<String, (Vec<Exchange>, usize)> = HashMap::new();
+        let mut count = 0;
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.context("Failed to read cassette index")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange: Exchange = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid cassette entry on line {}", n + 1))?;
+            let key = key(&exchange.method, &exchange.url);
+            exchanges.entry(key).or_default().0.push(exchange);
+            count += 1;
+        }
+
+        info!("Replaying {} HTTP exchanges from {:?}", count, dir);
+        Ok(Self {
+            mode: Mode::Replay {
+                dir: dir.to_path_buf(),
+                exchanges: Mutex::new(exchanges),
+            },
+        })
+    }
+
+    /// Cassette for `--record <dir>` / `--replay <dir>` (live when neither is given)
+    pub fn from_options(record: Option<&Path>, replay: Option<&Path>) -> Result<Self> {
+        match (record, replay) {
+            (Some(_), Some(_)) => anyhow::bail!("--record and --replay can't be used together"),
+            (Some(dir), None) => Self::record(dir),
+            (None, Some(dir)) => Self::replay(dir),
+            (None, None) => Ok(Self::live()),
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.mode, Mode::Replay { .. })
+    }
+
+    /// Send a request, recording or replaying it depending on the mode
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        self.send_with_limit(request, None).await
+    }
+
+    /// Like `send`, but a body recorded past `max_body_bytes` is stored as a
+    /// `body_too_large` failure instead of being buffered
+    ///
+    /// Live and replayed responses are returned unread; the caller enforces the
+    /// limit on them.
+    pub async fn send_with_limit(
+        &self,
+        request: RequestBuilder,
+        max_body_bytes: Option<u64>,
+    ) -> Result<Response, HttpError> {
+        match &self.mode {
+            Mode::Live => request.send().await.map_err(HttpError::Network),
+            Mode::Record { dir, index } => {
+                let (client, request) = request.build_split();
+                let request = request.map_err(HttpError::Network)?;
+                record(&client, request, dir, index, max_body_bytes).await
+            }
+            Mode::Replay { dir, exchanges } => {
+                let (_, request) = request.build_split();
+                let request = request.map_err(HttpError::Network)?;
+                let key = key(request.method().as_str(), request.url().as_str());
+
+                let exchange = {
+                    let mut exchanges = exchanges.lock().expect("Cassette poisoned");
+                    exchanges.get_mut(&key).map(|(list, next)| {
+                        let exchange = list[(*next).min(list.len() - 1)].clone();
+                        *next += 1;
+                        exchange
+                    })
+                };
+
+                match exchange {
+                    Some(exchange) => replay(dir, exchange),
+                    None => Err(HttpError::NotRecorded {
+                        method: request.method().to_string(),
+                        url: request.url().to_string(),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+fn key(method: &str, url: &str) -> String {
+    format!("{} {}", method, url)
+}
+
+/// Perform a live request, store the exchange and hand back an equivalent response
+async fn record(
+    client: &Client,
+    request: reqwest::Request,
+    dir: &Path,
+    index: &Mutex<File>,
+    max_body_bytes: Option<u64>,
+) -> Result<Response, HttpError> {
+    let mut exchange = Exchange {
+        method: request.method().to_string(),
+        url: request.url().to_string(),
+        recorded_at: Utc::now(),
+        status: None,
+        version: None,
+        headers: Vec::new(),
+        body_sha256: None,
+        error: None,
+    };
+
+    let mut response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(e) => return Err(record_failure(index, &mut exchange, HttpError::Network(e))),
+    };
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+
+    // Same cap as the downloader, read chunk by chunk so a huge body can't exhaust memory
+    if let (Some(limit), Some(declared)) = (max_body_bytes, response.content_length()) {
+        if declared > limit {
+            let error = HttpError::BodyTooLarge { size: declared, limit };
+            return Err(record_failure(index, &mut exchange, error));
+        }
+    }
+    let mut body: Vec<u8> = Vec::new();
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => return Err(record_failure(index, &mut exchange, HttpError::Network(e))),
+        };
+        body.extend_from_slice(&chunk);
+        if let Some(limit) = max_body_bytes {
+            if body.len() as u64 > limit {
+                let error = HttpError::BodyTooLarge { size: body.len() as u64, limit };
+                return Err(record_failure(index, &mut exchange, error));
+            }
+        }
+    }
+
+    let sha256 = format!("{:x}", Sha256::digest(&body));
+    let body_path = dir.join(BODIES_DIR).join(&sha256);
+    if !body_path.exists() {
+        // Write then rename, so a crash or a task recording the same body at the
+        // same time never leaves a truncated file under the hash
+        let tmp = dir
+            .join(BODIES_DIR)
+            .join(format!("{}.{:016x}.tmp", sha256, rand::random::<u64>()));
+        std::fs::write(&tmp, &body)
+            .and_then(|_| std::fs::rename(&tmp, &body_path))
+            .map_err(|e| HttpError::Cassette(format!("Failed to store body {}: {}", sha256, e)))?;
+    }
+
+    exchange.status = Some(status.as_u16());
+    exchange.version = Some(format!("{:?}", version));
+    exchange.headers = headers
+        .iter()
+        .map(|(name, value)| {
+            (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+        })
+        .collect();
+    exchange.body_sha256 = Some(sha256);
+    write_exchange(index, &exchange)?;
+    debug!("Recorded {} {} ({})", exchange.method, exchange.url, status);
+
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    *response.version_mut() = version;
+    *response.headers_mut() = headers;
+    Ok(Response::from(response))
+}
+
+/// Store a failed exchange and hand back its error
+fn record_failure(index: &Mutex<File>, exchange: &mut Exchange, error: HttpError) -> HttpError {
+    let kind = match &error {
+        HttpError::Network(e) => FailureKind::from_reqwest(e),
+        HttpError::BodyTooLarge { .. } => FailureKind::BodyTooLarge,
+        _ => FailureKind::Other,
+    };
+    exchange.error = Some(RecordedError {
+        kind: kind.as_str().to_string(),
+        message: error.to_string(),
+    });
+    match write_exchange(index, exchange) {
+        Ok(()) => error,
+        Err(e) => e,
+    }
+}
+
+fn write_exchange(index: &Mutex<File>, exchange: &Exchange) -> Result<(), HttpError> {
+    let mut line =
+        serde_json::to_string(exchange).map_err(|e| HttpError::Cassette(e.to_string()))?;
+    line.push('\n');
+
+    let mut index = index.lock().expect("Cassette index poisoned");
+    index
+        .write_all(line.as_bytes())
+        .map_err(|e| HttpError::Cassette(e.to_string()))
+}
+
+/// Rebuild the response (or error) of a recorded exchange
+fn replay(dir: &Path, exchange: Exchange) -> Result<Response, HttpError> {
+    if let Some(error) = exchange.error {
+        return Err(HttpError::Replayed {
+            kind: FailureKind::from_label(&error.kind).unwrap_or(FailureKind::Other),
+            message: error.message,
+        });
+    }
+
+    let body = match &exchange.body_sha256 {
+        Some(sha256) => {
+            let body = std::fs::read(dir.join(BODIES_DIR).join(sha256))
+                .map_err(|e| HttpError::Cassette(format!("Missing body {}: {}", sha256, e)))?;
+            if format!("{:x}", Sha256::digest(&body)) != *sha256 {
+                return Err(HttpError::Cassette(format!("Corrupt body {}", sha256)));
+            }
+            body
+        }
+        None => Vec::new(),
+    };
+
+    let mut response = http::Response::new(body);
+    *response.status_mut() = exchange
+        .status
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .unwrap_or(StatusCode::OK);
+    *response.version_mut() = match exchange.version.as_deref() {
+        Some("HTTP/1.0") => Version::HTTP_10,
+        Some("HTTP/2.0") => Version::HTTP_2,
+        Some("HTTP/3.0") => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    };
+    for (name, value) in &exchange.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+
+    Ok(Response::from(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_record_then_replay_without_network() {
+        let dir = std::env::temp_dir().join("jarvix_cassette_test");
+        std::fs::remove_dir_all(&dir).ok();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nX-Seen: 1\r\nConnection: close\r\n\r\n<p>hola</p>")
+                .await
+                .ok();
+        });
+
+        let client = Client::new();
+        let cassette = Cassette::record(&dir).unwrap();
+        let response = cassette.send(client.get(&url)).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "<p>hola</p>");
+        server.await.unwrap();
+
+        // The server is gone: replay must not need it
+        let cassette = Cassette::replay(&dir).unwrap();
+        for _ in 0..2 {
+            let response = cassette.send(client.get(&url)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["x-seen"], "1");
+            assert_eq!(response.text().await.unwrap(), "<p>hola</p>");
+        }
+
+        let missing = cassette.send(client.get("http://example.com/")).await;
+        assert!(matches!(missing, Err(HttpError::NotRecorded { .. })));
+
+        // A body file that no longer matches its hash is not served
+        let sha256 = format!("{:x}", Sha256::digest(b"<p>hola</p>"));
+        std::fs::write(dir.join(BODIES_DIR).join(sha256), "<p>ho").unwrap();
+        let cassette = Cassette::replay(&dir).unwrap();
+        let corrupt = cassette.send(client.get(&url)).await;
+        assert!(matches!(corrupt, Err(HttpError::Cassette(_))));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_record_caps_body_size() {
+        let dir = std::env::temp_dir().join("jarvix_cassette_limit_test");
+        std::fs::remove_dir_all(&dir).ok();
+
+        // No Content-Length, so the cap has to hold while streaming
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/big", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n")
+                .await
+                .ok();
+            socket.write_all(&[b'x'; 8192]).await.ok();
+        });
+
+        let client = Client::new();
+        let cassette = Cassette::record(&dir).unwrap();
+        let recorded = cassette.send_with_limit(client.get(&url), Some(1024)).await;
+        assert!(matches!(recorded, Err(HttpError::BodyTooLarge { limit: 1024, .. })));
+        server.await.unwrap();
+        assert_eq!(std::fs::read_dir(dir.join(BODIES_DIR)).unwrap().count(), 0);
+
+        let cassette = Cassette::replay(&dir).unwrap();
+        let replayed = cassette.send(client.get(&url)).await;
+        assert!(matches!(
+            replayed,
+            Err(HttpError::Replayed { kind: FailureKind::BodyTooLarge, .. })
+        ));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::db;
+use crate::parallel::DownloadResult;
+use crate::storage::{ParquetStorage, ResultWriter};
+
+/// Writer for collect runs that can be resumed after being killed
+///
+/// Results are written to numbered part files next to the run's output file.
+/// Once a part is complete it is renamed into place and its URLs are
+/// checkpointed in the database, so a resumed run can skip them. HTTP
+/// validators are stored at the same point, so they only ever reference
+/// bodies that are on disk. `close` merges all parts into the final
+/// `<run_id>.parquet`.
+pub struct CheckpointedWriter {
+    storage: ParquetStorage,
+    db_path: String,
+    run_id: String,
+    output_path: PathBuf,
+    parts_dir: PathBuf,
+    row_group_size: usize,
+    next_part: usize,
+    current: Option<ResultWriter>,
+    pending: Vec<(String, bool)>,
+    pending_validators: Vec<(String, db::Validators)>,
+    completed: HashSet<String>,
+    successful: usize,
+    written: usize,
+}
+
+impl CheckpointedWriter {
+    /// Open the writer for a run, resuming from its checkpoint if `resume` is set
+    pub fn open(
+        storage: &ParquetStorage,
+        db_path: &str,
+        run_id: &str,
+        output_path: &Path,
+        row_group_size: usize,
+        resume: bool,
+    ) -> Result<Self> {
+        let parts_dir = output_path.with_extension("parts");
+        let mut checkpoint = db::RunCheckpoint::default();
+
+        if resume {
+            checkpoint = db::get_checkpoint(db_path, run_id)
+                .context("Failed to load run checkpoint")?;
+            if checkpoint.completed.is_empty() {
+                warn!("No checkpoint found for run {}; starting from scratch", run_id);
+            } else {
+                info!(
+                    "Resuming run {}: {} URLs already collected",
+                    run_id,
+                    checkpoint.completed.len()
+                );
+            }
+        } else {
+            db::clear_checkpoint(db_path, run_id).context("Failed to clear run checkpoint")?;
+            if parts_dir.exists() {
+                std::fs::remove_dir_all(&parts_dir)
+                    .context("Failed to remove stale part files")?;
+            }
+        }
+
+        std::fs::create_dir_all(&parts_dir).context("Failed to create parts directory")?;
+        let next_part = checkpoint.last_part.map_or(0, |p| p + 1);
+
+        // Parts written but never checkpointed were interrupted; their URLs get refetched
+        for part in list_parts(&parts_dir)? {
+            if part_number(&part).is_none_or(|n| n >= next_part) {
+                debug!("Removing unrecorded part {:?}", part);
+                std::fs::remove_file(&part).ok();
+            }
+        }
+
+        Ok(Self {
+            storage: storage.clone(),
+            db_path: db_path.to_string(),
+            run_id: run_id.to_string(),
+            output_path: output_path.to_path_buf(),
+            parts_dir,
+            row_group_size: row_group_size.max(1),
+            next_part,
+            current: None,
+            pending: Vec::new(),
+            pending_validators: Vec::new(),
+            written: checkpoint.completed.len(),
+            successful: checkpoint.successful,
+            completed: checkpoint.completed,
+        })
+    }
+
+    /// URLs that already have a stored result in this run
+    pub fn completed_urls(&self) -> &HashSet<String> {
+        &self.completed
+    }
+
+    /// Add one result; a part is committed every `row_group_size` results
+    pub fn write(&mut self, result: DownloadResult) -> Result<()> {
+        if self.current.is_none() {
+            let path = self.part_path(self.next_part).with_extension("parquet.tmp");
+            self.current = Some(self.storage.open_results_writer(&path, self.row_group_size)?);
+        }
+
+        if result.success {
+            self.successful += 1;
+        }
+        self.written += 1;
+        self.pending.push((result.url.clone(), result.success));
+        if let Some(validators) = result.validators(&self.run_id) {
+            self.pending_validators.push((result.url.clone(), validators));
+        }
+
+        if let Some(writer) = self.current.as_mut() {
+            writer.write(result)?;
+        }
+
+        if self.pending.len() >= self.row_group_size {
+            self.commit_part()?;
+        }
+
+        Ok(())
+    }
+
+    /// Close the current part, move it into place and checkpoint its URLs
+    fn commit_part(&mut self) -> Result<()> {
+        let Some(writer) = self.current.take() else {
+            return Ok(());
+        };
+
+        let part = self.next_part;
+        let tmp_path = self.part_path(part).with_extension("parquet.tmp");
+        writer.close()?;
+        std::fs::rename(&tmp_path, self.part_path(part)).context("Failed to commit part file")?;
+
+        db::checkpoint_urls(&self.db_path, &self.run_id, part, &self.pending)
+            .context("Failed to checkpoint completed URLs")?;
+        db::save_validators(&self.db_path, &self.pending_validators)
+            .context("Failed to store HTTP validators")?;
+        self.pending_validators.clear();
+        debug!("Checkpointed part {} ({} URLs)", part, self.pending.len());
+
+        self.completed.extend(self.pending.drain(..).map(|(url, _)| url));
+        self.next_part += 1;
+
+        Ok(())
+    }
+
+    /// Results stored for this run, including ones from before a resume
+    pub fn len(&self) -> usize {
+        self.written
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+
+    /// Successful results stored for this run
+    pub fn successful(&self) -> usize {
+        self.successful
+    }
+
+    /// Commit the last part, merge all parts into the run file and drop the checkpoint
+    pub fn close(mut self) -> Result<usize> {
+        self.commit_part()?;
+
+        let parts = list_parts(&self.parts_dir)?;
+        let rows = self.storage.merge_results(&parts, &self.output_path)?;
+
+        std::fs::remove_dir_all(&self.parts_dir).context("Failed to remove part files")?;
+        db::clear_checkpoint(&self.db_path, &self.run_id)
+            .context("Failed to clear run checkpoint")?;
+
+        Ok(rows)
+    }
+
+    /// Commit the last part and write the run file from all parts so far
+    ///
+    /// Unlike `close`, the parts and the checkpoint are kept, so an
+    /// interrupted run can still be continued with `--resume`; the run file
+    /// is rewritten when the resumed run closes.
+    pub fn suspend(mut self) -> Result<usize> {
+        self.commit_part()?;
+
+        let parts = list_parts(&self.parts_dir)?;
+        self.storage.merge_results(&parts, &self.output_path)
+    }
+
+    fn part_path(&self, part: usize) -> PathBuf {
+        self.parts_dir.join(format!("part-{:05}.parquet", part))
+    }
+}
+
+/// Part files of a run (committed and uncommitted), sorted by part number
+fn list_parts(parts_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(parts_dir)
+        .context("Failed to list part files")?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("part-"))
+        })
+        .collect();
+
+    parts.sort();
+    Ok(parts)
+}
+
+/// Part number of a committed part file (None for temporary files)
+fn part_number(path: &Path) -> Option<usize> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("part-")?
+        .strip_suffix(".parquet")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(url: &str) -> DownloadResult {
+        DownloadResult {
+            url: url.to_string(),
+            success: true,
+            content: Some("<html>test</html>".to_string()),
+            status_code: Some(200),
+            attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resume_skips_checkpointed_urls() {
+        let temp_dir = std::env::temp_dir().join("jarvix_checkpoint_test");
+        std::fs::remove_dir_all(&temp_dir).ok();
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let db_path = temp_dir.join("jarvix.db");
+        let db_path = db_path.to_str().unwrap();
+        db::migrate(db_path).unwrap();
+
+        let storage = ParquetStorage::new();
+        let output_path = temp_dir.join("raw").join("run.parquet");
+
+        // First run is "killed" after one full part and one partial part
+        let mut writer =
+            CheckpointedWriter::open(&storage, db_path, "run", &output_path, 2, false).unwrap();
+        for i in 0..3 {
+            writer.write(result(&format!("https://example.com/{}", i))).unwrap();
+        }
+        drop(writer);
+
+        let writer =
+            CheckpointedWriter::open(&storage, db_path, "run", &output_path, 2, true).unwrap();
+        assert_eq!(writer.completed_urls().len(), 2);
+        assert!(writer.completed_urls().contains("https://example.com/0"));
+        assert!(!writer.completed_urls().contains("https://example.com/2"));
+
+        let mut writer = writer;
+        writer.write(result("https://example.com/2")).unwrap();
+        assert_eq!(writer.close().unwrap(), 3);
+        assert!(output_path.exists());
+        assert!(db::get_checkpoint(db_path, "run").unwrap().completed.is_empty());
+
+        std::fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_rerun_does_not_revalidate_against_its_own_bodies() {
+        let temp_dir = std::env::temp_dir().join("jarvix_rerun_validators_test");
+        std::fs::remove_dir_all(&temp_dir).ok();
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let db_path = temp_dir.join("jarvix.db");
+        let db_path = db_path.to_str().unwrap();
+        db::migrate(db_path).unwrap();
+
+        let storage = ParquetStorage::new();
+        let output_path = temp_dir.join("raw").join("run.parquet");
+        let url = "https://example.com/0".to_string();
+
+        let mut writer =
+            CheckpointedWriter::open(&storage, db_path, "run", &output_path, 10, false).unwrap();
+        writer
+            .write(DownloadResult {
+                etag: Some("\"v1\"".to_string()),
+                ..result(&url)
+            })
+            .unwrap();
+        writer.close().unwrap();
+
+        // Another run may send a conditional request; a rerun of "run" rewrites
+        // the file holding the body, so it has to fetch it in full
+        let urls = [url.clone()];
+        let other = db::get_validators(db_path, &urls, "next").unwrap();
+        assert_eq!(other[&url].content_run_id, "run");
+        assert!(db::get_validators(db_path, &urls, "run").unwrap().is_empty());
+
+        std::fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    #[test]
+    fn test_suspend_keeps_checkpoint() {
+        let temp_dir = std::env::temp_dir().join("jarvix_suspend_test");
+        std::fs::remove_dir_all(&temp_dir).ok();
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let db_path = temp_dir.join("jarvix.db");
+        let db_path = db_path.to_str().unwrap();
+        db::migrate(db_path).unwrap();
+
+        let storage = ParquetStorage::new();
+        let output_path = temp_dir.join("raw").join("run.parquet");
+
+        let mut writer =
+            CheckpointedWriter::open(&storage, db_path, "run", &output_path, 10, false).unwrap();
+        writer.write(result("https://example.com/0")).unwrap();
+        writer.write(result("https://example.com/1")).unwrap();
+        assert_eq!(writer.suspend().unwrap(), 2);
+        assert!(output_path.exists());
+
+        // Partial part was committed, so both URLs are skipped on resume
+        let writer =
+            CheckpointedWriter::open(&storage, db_path, "run", &output_path, 10, true).unwrap();
+        assert_eq!(writer.completed_urls().len(), 2);
+
+        std::fs::remove_dir_all(&temp_dir).ok();
+    }
+}
@@ -0,0 +1,522 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, info};
+
+use crate::parallel::DownloadResult;
+use crate::politeness;
+use crate::storage::{ParquetStorage, ReadOptions, ResultWriter, DEFAULT_ROW_GROUP_SIZE};
+
+/// Name of the manifest `compact` writes at the dataset root
+pub const MANIFEST_FILE: &str = "_manifest.json";
+
+/// Where a collect run stores its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// One `raw/<run_id>.parquet` file per run
+    #[default]
+    Flat,
+    /// Hive-style `raw/date=.../run_id=.../domain_bucket=.../part-NNNNN.parquet`
+    Partitioned,
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "flat" => Ok(Layout::Flat),
+            "partitioned" => Ok(Layout::Partitioned),
+            other => bail!("Unknown layout '{}' (flat or partitioned)", other),
+        }
+    }
+}
+
+/// Hive partition of a raw result
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Partition {
+    /// UTC day the page was fetched
+    pub date: NaiveDate,
+    pub run_id: String,
+    /// Hash of the host, so one domain's pages share a bucket
+    pub domain_bucket: u32,
+}
+
+impl Partition {
+    /// Partition of `result`; `fallback_date` is used when it was never fetched
+    pub fn of(result: &DownloadResult, run_id: &str, fallback_date: NaiveDate, buckets: u32) -> Self {
+        Self {
+            date: result.fetched_at.map_or(fallback_date, |at| at.date_naive()),
+            run_id: run_id.to_string(),
+            domain_bucket: domain_bucket(&result.url, buckets),
+        }
+    }
+
+    /// Directory of the partition, relative to the dataset root
+    pub fn dir(&self) -> PathBuf {
+        PathBuf::from(format!("date={}", self.date))
+            .join(format!("run_id={}", self.run_id))
+            .join(format!("domain_bucket={:02}", self.domain_bucket))
+    }
+
+    /// Partition of a directory relative to the dataset root, e.g.
+    /// "date=2025-06-02/run_id=r1/domain_bucket=03"
+    pub fn parse(dir: &Path) -> Result<Self> {
+        let mut values = HashMap::new();
+        for component in dir.components() {
+            let component = component.as_os_str().to_string_lossy();
+            let Some((key, value)) = component.split_once('=') else {
+                bail!("Not a partition directory: {:?}", dir);
+            };
+            values.insert(key.to_string(), value.to_string());
+        }
+
+        let value = |key: &str| {
+            values
+                .get(key)
+                .with_context(|| format!("Partition {:?} has no {}", dir, key))
+        };
+        Ok(Self {
+            date: value("date")?.parse().context("Invalid partition date")?,
+            run_id: value("run_id")?.clone(),
+            domain_bucket: value("domain_bucket")?.parse().context("Invalid domain bucket")?,
+        })
+    }
+}
+
+/// Bucket of a URL's host, stable across builds and platforms
+pub fn domain_bucket(url: &str, buckets: u32) -> u32 {
+    let digest = Sha256::digest(politeness::host_key(url));
+    let mut hash = [0u8; 4];
+    hash.copy_from_slice(&digest[..4]);
+    u32::from_le_bytes(hash) % buckets.max(1)
+}
+
+/// Split a flat run file into partitions under `root`
+///
+/// Partitions previously written for `run_id` are replaced. Returns the
+/// number of rows written.
+pub fn write_run(
+    storage: &ParquetStorage,
+    run_file: &Path,
+    root: &Path,
+    run_id: &str,
+    fallback_date: NaiveDate,
+    buckets: u32,
+) -> Result<usize> {
+    remove_run(root, run_id)?;
+
+    let mut writers: BTreeMap<Partition, ResultWriter> = BTreeMap::new();
+    let mut rows = 0;
+    for batch in storage.open_reader::<DownloadResult, _>(run_file, &ReadOptions::default())? {
+        for result in batch? {
+            let partition = Partition::of(&result, run_id, fallback_date, buckets);
+            let writer = match writers.get_mut(&partition) {
+                Some(writer) => writer,
+                None => {
+                    let path = next_part_path(&root.join(partition.dir()))?;
+                    let writer = storage.open_results_writer(path, DEFAULT_ROW_GROUP_SIZE)?;
+                    writers.entry(partition).or_insert(writer)
+                }
+            };
+            writer.write(result)?;
+            rows += 1;
+        }
+    }
+
+    let partitions = writers.len();
+    for writer in writers.into_values() {
+        writer.close()?;
+    }
+    info!("Wrote run {} as {} rows in {} partitions under {:?}", run_id, rows, partitions, root);
+
+    Ok(rows)
+}
+
+/// Delete every partition of `run_id`
+fn remove_run(root: &Path, run_id: &str) -> Result<()> {
+    for date_dir in partition_dirs(root, "date")? {
+        let run_dir = date_dir.join(format!("run_id={}", run_id));
+        if run_dir.exists() {
+            debug!("Replacing {:?}", run_dir);
+            std::fs::remove_dir_all(&run_dir)
+                .with_context(|| format!("Failed to remove {:?}", run_dir))?;
+        }
+    }
+    Ok(())
+}
+
+/// Subdirectories of `dir` named `<key>=...`
+fn partition_dirs(dir: &Path, key: &str) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}=", key);
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {:?}", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+        })
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Parquet files directly in `dir`, sorted by name
+fn parquet_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {:?}", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "parquet"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// First unused `part-NNNNN.parquet` in a partition directory
+fn next_part_path(dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    (0..)
+        .map(|n| dir.join(format!("part-{:05}.parquet", n)))
+        .find(|path| !path.exists())
+        .context("No free part file name")
+}
+
+/// Leaf partition directories of a dataset
+fn leaf_partitions(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut leaves = Vec::new();
+    for date_dir in partition_dirs(root, "date")? {
+        for run_dir in partition_dirs(&date_dir, "run_id")? {
+            leaves.extend(partition_dirs(&run_dir, "domain_bucket")?);
+        }
+    }
+    Ok(leaves)
+}
+
+/// Settings of a `compact` run
+#[derive(Debug, Clone)]
+pub struct CompactConfig {
+    /// Files with fewer rows than this are merged, into files of up to this many rows
+    pub target_rows: usize,
+    /// Domain buckets used when partitioning flat run files
+    pub domain_buckets: u32,
+}
+
+impl Default for CompactConfig {
+    fn default() -> Self {
+        Self {
+            target_rows: 100_000,
+            domain_buckets: 16,
+        }
+    }
+}
+
+/// One data file of a partitioned dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the dataset root
+    pub path: String,
+    #[serde(flatten)]
+    pub partition: Partition,
+    pub rows: usize,
+    pub bytes: u64,
+}
+
+/// Files and row counts of a partitioned dataset, written by `compact`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub generated_at: DateTime<Utc>,
+    pub domain_buckets: u32,
+    pub total_rows: usize,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write manifest {:?}", path))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid manifest {:?}", path))
+    }
+}
+
+/// Move flat run files into the partitioned layout, merge small files per
+/// partition and write the manifest
+///
+/// Flat `<run_id>.parquet` files directly under `root` are partitioned by
+/// fetch date (their modification date when a row was never fetched).
+pub fn compact(storage: &ParquetStorage, root: &Path, config: &CompactConfig) -> Result<Manifest> {
+    std::fs::create_dir_all(root).with_context(|| format!("Failed to create {:?}", root))?;
+
+    for run_file in parquet_files(root)? {
+        let Some(run_id) = run_file.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let modified: DateTime<Utc> = std::fs::metadata(&run_file)?.modified()?.into();
+        info!("Partitioning flat run file {:?}", run_file);
+        // write_run replaces the run's partitions, so a compact interrupted
+        // before the flat file is removed repeats this step without duplicating rows
+        write_run(
+            storage,
+            &run_file,
+            root,
+            &run_id,
+            modified.date_naive(),
+            config.domain_buckets,
+        )?;
+        std::fs::remove_file(&run_file)
+            .with_context(|| format!("Failed to remove {:?}", run_file))?;
+    }
+
+    let target_rows = config.target_rows.max(1);
+    let mut files = Vec::new();
+    for leaf in leaf_partitions(root)? {
+        let partition = Partition::parse(leaf.strip_prefix(root)?)?;
+
+        let mut small = Vec::new();
+        for file in parquet_files(&leaf)? {
+            if storage.row_count(&file)? < target_rows {
+                small.push(file);
+            }
+        }
+        if small.len() > 1 {
+            merge_files(storage, &small, &leaf, target_rows)?;
+        }
+
+        for file in parquet_files(&leaf)? {
+            files.push(ManifestEntry {
+                path: file
+                    .strip_prefix(root)?
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                partition: partition.clone(),
+                rows: storage.row_count(&file)?,
+                bytes: std::fs::metadata(&file)?.len(),
+            });
+        }
+    }
+
+    let manifest = Manifest {
+        generated_at: Utc::now(),
+        domain_buckets: config.domain_buckets,
+        total_rows: files.iter().map(|f| f.rows).sum(),
+        files,
+    };
+    manifest.save(&root.join(MANIFEST_FILE))?;
+    info!(
+        "Compacted {:?}: {} rows in {} files",
+        root,
+        manifest.total_rows,
+        manifest.files.len()
+    );
+
+    Ok(manifest)
+}
+
+/// Merge `inputs` into files of up to `target_rows` rows in `dir`
+fn merge_files(
+    storage: &ParquetStorage,
+    inputs: &[PathBuf],
+    dir: &Path,
+    target_rows: usize,
+) -> Result<()> {
+    debug!("Merging {} small files in {:?}", inputs.len(), dir);
+
+    // Write next to the inputs first, so a failed write leaves them intact
+    let mut outputs: Vec<PathBuf> = Vec::new();
+    let mut writer: Option<ResultWriter> = None;
+    for input in inputs {
+        for batch in storage.open_reader::<DownloadResult, _>(input, &ReadOptions::default())? {
+            for result in batch? {
+                let current = match writer.as_mut() {
+                    Some(current) => current,
+                    None => {
+                        let path = dir.join(format!("merge-{:05}.parquet.tmp", outputs.len()));
+                        outputs.push(path.clone());
+                        writer.insert(storage.open_results_writer(path, DEFAULT_ROW_GROUP_SIZE)?)
+                    }
+                };
+                current.write(result)?;
+                if current.len() >= target_rows {
+                    if let Some(full) = writer.take() {
+                        full.close()?;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(last) = writer {
+        last.close()?;
+    }
+
+    replace_files(inputs, &outputs, dir)
+}
+
+/// Move merged `outputs` into place as part files of `dir`, then delete `inputs`
+///
+/// The inputs go only once every output is in place, so a failure can leave
+/// rows twice but never loses them.
+fn replace_files(inputs: &[PathBuf], outputs: &[PathBuf], dir: &Path) -> Result<()> {
+    for output in outputs {
+        std::fs::rename(output, next_part_path(dir)?)
+            .with_context(|| format!("Failed to move {:?} into place", output))?;
+    }
+    for input in inputs {
+        std::fs::remove_file(input).with_context(|| format!("Failed to remove {:?}", input))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn result(url: &str, day: u32) -> DownloadResult {
+        DownloadResult {
+            url: url.to_string(),
+            success: true,
+            status_code: Some(200),
+            fetched_at: Some(Utc.with_ymd_and_hms(2025, 6, day, 12, 0, 0).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_partition_paths_round_trip() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let partition = Partition::of(&result("https://shop.example.com/a", 2), "r1", date, 16);
+        assert_eq!(
+            partition.domain_bucket,
+            domain_bucket("https://shop.example.com/other", 16)
+        );
+        assert!(partition.dir().starts_with("date=2025-06-02/run_id=r1"));
+        assert_eq!(Partition::parse(&partition.dir()).unwrap(), partition);
+
+        let unfetched = DownloadResult::skipped_deadline("https://shop.example.com/b");
+        assert_eq!(Partition::of(&unfetched, "r1", date, 16).date, date);
+    }
+
+    #[test]
+    fn test_compact_partitions_runs_and_merges_small_files() {
+        let root = std::env::temp_dir().join("jarvix_test_compact");
+        std::fs::remove_dir_all(&root).ok();
+        let storage = ParquetStorage::new();
+        let date = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+
+        // A flat run file, and a partitioned run that landed in two small files
+        let flat: Vec<DownloadResult> = (0..20)
+            .map(|i| result(&format!("https://site{}.test/", i % 5), 1 + i as u32 % 2))
+            .collect();
+        storage.save_results(&flat, root.join("old.parquet")).unwrap();
+
+        let run_file = root.join("new.parquet");
+        storage.save_results(&[result("https://a.test/1", 3)], &run_file).unwrap();
+        write_run(&storage, &run_file, &root, "new", date, 1).unwrap();
+        storage.save_results(&[result("https://a.test/2", 3)], &run_file).unwrap();
+        let leaf = root.join("date=2025-06-03/run_id=new/domain_bucket=00");
+        std::fs::rename(&run_file, leaf.join("part-00001.parquet")).unwrap();
+
+        let config = CompactConfig {
+            target_rows: 1000,
+            domain_buckets: 4,
+        };
+        let manifest = compact(&storage, &root, &config).unwrap();
+
+        assert_eq!(manifest.total_rows, 22);
+        assert!(!root.join("old.parquet").exists());
+        assert_eq!(parquet_files(&leaf).unwrap().len(), 1);
+        let merged = manifest
+            .files
+            .iter()
+            .find(|f| f.partition.run_id == "new")
+            .unwrap();
+        assert_eq!(merged.rows, 2);
+        assert!(manifest
+            .files
+            .iter()
+            .filter(|f| f.partition.run_id == "old")
+            .all(|f| f.partition.domain_bucket < 4));
+        let days: std::collections::BTreeSet<_> =
+            manifest.files.iter().map(|f| f.partition.date.to_string()).collect();
+        assert_eq!(days.len(), 3);
+
+        assert_eq!(Manifest::load(&root.join(MANIFEST_FILE)).unwrap(), manifest);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_interrupted_compact_does_not_duplicate_rows() {
+        let root = std::env::temp_dir().join("jarvix_test_compact_interrupted");
+        std::fs::remove_dir_all(&root).ok();
+        let storage = ParquetStorage::new();
+        let date = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+
+        let flat: Vec<DownloadResult> = (0..10)
+            .map(|i| result(&format!("https://site{}.test/", i % 3), 1 + i as u32 % 2))
+            .collect();
+        let run_file = root.join("r1.parquet");
+        storage.save_results(&flat, &run_file).unwrap();
+
+        // A compact that stopped after partitioning the run, before removing its flat file
+        write_run(&storage, &run_file, &root, "r1", date, 4).unwrap();
+        assert!(run_file.exists());
+
+        let config = CompactConfig {
+            target_rows: 1000,
+            domain_buckets: 4,
+        };
+        let manifest = compact(&storage, &root, &config).unwrap();
+        assert_eq!(manifest.total_rows, 10);
+        assert!(!run_file.exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_failed_merge_keeps_every_row() {
+        let dir = std::env::temp_dir().join("jarvix_test_merge_failure");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = ParquetStorage::new();
+
+        let inputs = vec![dir.join("part-00000.parquet"), dir.join("part-00001.parquet")];
+        storage.save_results(&[result("https://a.test/1", 3)], &inputs[0]).unwrap();
+        storage.save_results(&[result("https://a.test/2", 3)], &inputs[1]).unwrap();
+        let merged = dir.join("merge-00000.parquet.tmp");
+        storage
+            .save_results(&[result("https://a.test/1", 3), result("https://a.test/2", 3)], &merged)
+            .unwrap();
+
+        // The second output is gone, so its rename fails after the first succeeded
+        let outputs = vec![merged, dir.join("merge-00001.parquet.tmp")];
+        assert!(replace_files(&inputs, &outputs, &dir).is_err());
+
+        assert!(inputs.iter().all(|input| input.exists()));
+        let mut urls = std::collections::BTreeSet::new();
+        for file in parquet_files(&dir).unwrap() {
+            for batch in storage
+                .open_reader::<DownloadResult, _>(&file, &ReadOptions::default())
+                .unwrap()
+            {
+                urls.extend(batch.unwrap().into_iter().map(|r| r.url));
+            }
+        }
+        assert_eq!(urls.len(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
 use chrono::Utc;
 
 /// Initialize the SQLite database with required tables
@@ -60,6 +61,31 @@ pub fn migrate(db_path: &str) -> Result<()> {
         [],
     )?;
     
+    // Completed URLs of collect runs, for resuming interrupted runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS collect_checkpoints (
+            run_id TEXT NOT NULL,
+            url TEXT NOT NULL,
+            part INTEGER NOT NULL,
+            success INTEGER NOT NULL,
+            completed_at TEXT NOT NULL,
+            PRIMARY KEY (run_id, url)
+        )",
+        [],
+    )?;
+    
+    // HTTP validators from previous runs, for conditional re-fetching
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS http_validators (
+            url TEXT PRIMARY KEY,
+            etag TEXT,
+            last_modified TEXT,
+            content_run_id TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
     Ok(())
 }
 
@@ -85,6 +111,34 @@ pub fn log_event(
     Ok(())
 }
 
+/// Database and run an engine component writes its events to
+#[derive(Debug, Clone)]
+pub struct EventSink {
+    pub db_path: String,
+    pub run_id: String,
+}
+
+impl EventSink {
+    pub fn new(db_path: &str, run_id: &str) -> Self {
+        Self {
+            db_path: db_path.to_string(),
+            run_id: run_id.to_string(),
+        }
+    }
+
+    /// Log an event for this sink's run
+    pub fn log(
+        &self,
+        event_type: &str,
+        url: Option<&str>,
+        status: &str,
+        message: &str,
+        metadata: Option<&str>,
+    ) -> Result<()> {
+        log_event(&self.db_path, &self.run_id, event_type, url, status, message, metadata)
+    }
+}
+
 /// Check if a domain is already cached for a given niche and region
 pub fn check_cache(
     db_path: &str,
@@ -144,3 +198,140 @@ pub fn get_cached_domains(
     
     Ok(domains)
 }
+
+/// Record URLs whose results were durably written to part `part` of a run
+pub fn checkpoint_urls(
+    db_path: &str,
+    run_id: &str,
+    part: usize,
+    urls: &[(String, bool)],
+) -> Result<()> {
+    let mut conn = Connection::open(db_path)?;
+    let timestamp = Utc::now().to_rfc3339();
+    
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO collect_checkpoints (run_id, url, part, success, completed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        for (url, success) in urls {
+            stmt.execute(params![run_id, url, part as i64, *success as i32, timestamp])?;
+        }
+    }
+    tx.commit()?;
+    
+    Ok(())
+}
+
+/// Checkpointed state of a collect run
+#[derive(Debug, Clone, Default)]
+pub struct RunCheckpoint {
+    /// URLs that already have a stored result
+    pub completed: HashSet<String>,
+    /// How many of them were successful
+    pub successful: usize,
+    /// Highest part number recorded, if any
+    pub last_part: Option<usize>,
+}
+
+/// Load the checkpoint of a collect run
+pub fn get_checkpoint(db_path: &str, run_id: &str) -> Result<RunCheckpoint> {
+    let conn = Connection::open(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT url, part, success FROM collect_checkpoints WHERE run_id = ?1"
+    )?;
+    
+    let mut checkpoint = RunCheckpoint::default();
+    let rows = stmt.query_map(params![run_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i32>(2)?))
+    })?;
+    
+    for row in rows {
+        let (url, part, success) = row?;
+        if success != 0 {
+            checkpoint.successful += 1;
+        }
+        let part = part as usize;
+        checkpoint.last_part = Some(checkpoint.last_part.map_or(part, |p| p.max(part)));
+        checkpoint.completed.insert(url);
+    }
+    
+    Ok(checkpoint)
+}
+
+/// Remove the checkpoint of a collect run
+pub fn clear_checkpoint(db_path: &str, run_id: &str) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+    conn.execute(
+        "DELETE FROM collect_checkpoints WHERE run_id = ?1",
+        params![run_id],
+    )?;
+    
+    Ok(())
+}
+
+/// HTTP cache validators of a URL and the run whose raw file holds its body
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_run_id: String,
+}
+
+/// Get stored validators for the given URLs, for a collect into `run_id`
+///
+/// Validators whose body is stored in `run_id` itself are left out: the run
+/// rewrites that raw file, so a 304 would point at a body it just deleted.
+pub fn get_validators(
+    db_path: &str,
+    urls: &[String],
+    run_id: &str,
+) -> Result<HashMap<String, Validators>> {
+    let conn = Connection::open(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT etag, last_modified, content_run_id FROM http_validators WHERE url = ?1"
+    )?;
+    
+    let mut validators = HashMap::new();
+    for url in urls {
+        let row = stmt.query_row(params![url], |row| {
+            Ok(Validators {
+                etag: row.get(0)?,
+                last_modified: row.get(1)?,
+                content_run_id: row.get(2)?,
+            })
+        });
+        
+        match row {
+            Ok(v) if v.content_run_id == run_id => {}
+            Ok(v) => {
+                validators.insert(url.clone(), v);
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    
+    Ok(validators)
+}
+
+/// Store validators for URLs whose bodies are now durably stored
+pub fn save_validators(db_path: &str, validators: &[(String, Validators)]) -> Result<()> {
+    let mut conn = Connection::open(db_path)?;
+    let timestamp = Utc::now().to_rfc3339();
+    
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO http_validators (url, etag, last_modified, content_run_id, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)"
+        )?;
+        for (url, v) in validators {
+            stmt.execute(params![url, v.etag, v.last_modified, v.content_run_id, timestamp])?;
+        }
+    }
+    tx.commit()?;
+    
+    Ok(())
+}
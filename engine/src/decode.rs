@@ -0,0 +1,154 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+/// How many leading bytes are scanned for a `<meta>` charset declaration
+const META_PRESCAN_BYTES: usize = 4096;
+
+/// Where the encoding of a body was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// Byte order mark at the start of the body
+    Bom,
+    /// `charset` parameter of the `Content-Type` header
+    Header,
+    /// `<meta charset>` or `<meta http-equiv="Content-Type">` in the document
+    Meta,
+    /// Guessed from the bytes themselves
+    Detected,
+}
+
+/// Response body decoded to UTF-8
+#[derive(Debug, Clone)]
+pub struct DecodedBody {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub source: EncodingSource,
+}
+
+/// Charset declared in a `Content-Type` header value, if any
+pub fn header_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches('"').as_bytes()))
+}
+
+/// Charset declared in a `<meta>` tag near the start of an HTML document
+pub fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    static META: OnceLock<Regex> = OnceLock::new();
+    let meta = META.get_or_init(|| {
+        Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#)
+            .expect("Invalid meta charset regex")
+    });
+
+    let head = &body[..body.len().min(META_PRESCAN_BYTES)];
+    let label = meta.captures(head)?.get(1)?.as_bytes();
+    let encoding = Encoding::for_label(label)?;
+
+    // A document we could read the tag from as ASCII is not UTF-16 (HTML spec)
+    if encoding == UTF_16BE || encoding == UTF_16LE {
+        Some(UTF_8)
+    } else {
+        Some(encoding)
+    }
+}
+
+/// Pick the encoding of a body: BOM, then header, then `<meta>`, then detection
+pub fn sniff_encoding(
+    body: &[u8],
+    content_type: Option<&str>,
+) -> (&'static Encoding, EncodingSource) {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return (encoding, EncodingSource::Bom);
+    }
+
+    if let Some(encoding) = content_type.and_then(header_charset) {
+        return (encoding, EncodingSource::Header);
+    }
+
+    if let Some(encoding) = meta_charset(body) {
+        return (encoding, EncodingSource::Meta);
+    }
+
+    if std::str::from_utf8(body).is_ok() {
+        return (UTF_8, EncodingSource::Detected);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(body, true);
+    (detector.guess(None, true), EncodingSource::Detected)
+}
+
+/// Decode a response body to UTF-8, sniffing its encoding
+///
+/// Bytes that are invalid in the chosen encoding are replaced with U+FFFD,
+/// so the result is always valid UTF-8.
+pub fn decode_body(body: &[u8], content_type: Option<&str>) -> DecodedBody {
+    let (encoding, source) = sniff_encoding(body, content_type);
+    // `decode` strips a BOM and switches encoding if it finds one
+    let (text, encoding, _) = encoding.decode(body);
+
+    DecodedBody {
+        text: text.into_owned(),
+        encoding,
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+
+    #[test]
+    fn test_decode_uses_header_charset() {
+        // "año" in ISO-8859-1
+        let body = [0x61, 0xF1, 0x6F];
+        let decoded = decode_body(&body, Some("text/html; charset=ISO-8859-1"));
+        assert_eq!(decoded.text, "año");
+        assert_eq!(decoded.source, EncodingSource::Header);
+
+        let decoded = decode_body("año".as_bytes(), Some("text/html"));
+        assert_eq!(decoded.text, "año");
+        assert_eq!(decoded.encoding, UTF_8);
+    }
+
+    #[test]
+    fn test_decode_uses_meta_charset() {
+        let (html, _, _) = SHIFT_JIS.encode(
+            "<html><head><meta charset=\"Shift_JIS\"><title>日本語のページ</title></head></html>",
+        );
+        let decoded = decode_body(&html, Some("text/html"));
+        assert_eq!(decoded.encoding, SHIFT_JIS);
+        assert_eq!(decoded.source, EncodingSource::Meta);
+        assert!(decoded.text.contains("日本語のページ"));
+
+        let http_equiv =
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">";
+        assert_eq!(meta_charset(http_equiv), Some(WINDOWS_1252));
+    }
+
+    #[test]
+    fn test_bom_wins_over_header() {
+        let mut body = vec![0xEF, 0xBB, 0xBF];
+        body.extend_from_slice("año".as_bytes());
+        let decoded = decode_body(&body, Some("text/html; charset=ISO-8859-1"));
+        assert_eq!(decoded.text, "año");
+        assert_eq!(decoded.source, EncodingSource::Bom);
+    }
+
+    #[test]
+    fn test_detects_undeclared_encoding() {
+        let (body, _, _) = SHIFT_JIS.encode(
+            "<html><body><p>東京都の天気予報です。明日は晴れのち曇りでしょう。</p></body></html>",
+        );
+        let decoded = decode_body(&body, None);
+        assert_eq!(decoded.encoding, SHIFT_JIS);
+        assert_eq!(decoded.source, EncodingSource::Detected);
+        assert!(decoded.text.contains("東京都の天気予報"));
+    }
+}
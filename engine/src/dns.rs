@@ -0,0 +1,263 @@
+use anyhow::{bail, Context, Result};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Name resolution settings for downloads
+#[derive(Debug, Clone)]
+pub struct DnsConfig {
+    /// Fixed addresses per host, like curl's `--resolve`; never looked up
+    pub overrides: HashMap<String, Vec<IpAddr>>,
+    /// How long a successful lookup is reused, in seconds (0 disables caching)
+    pub cache_ttl_secs: u64,
+    /// How long a failed lookup is remembered, in seconds
+    pub negative_ttl_secs: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            overrides: HashMap::new(),
+            cache_ttl_secs: 300,
+            negative_ttl_secs: 30,
+        }
+    }
+}
+
+/// Parse a curl-style `host:ip` (or `host:port:ip`) override
+///
+/// Several addresses can be given comma-separated; IPv6 addresses may be
+/// wrapped in brackets. The port, if present, is ignored: overrides apply
+/// to every port of the host.
+pub fn parse_override(spec: &str) -> Result<(String, Vec<IpAddr>)> {
+    let Some((host, rest)) = spec.split_once(':') else {
+        bail!("Invalid --resolve '{}': expected host:ip", spec);
+    };
+
+    // `host:port:ip` unless the remainder is itself an address (e.g. bare IPv6)
+    let addresses = match rest.split_once(':') {
+        Some((port, addresses)) if port.parse::<u16>().is_ok() => addresses,
+        _ => rest,
+    };
+
+    let ips = addresses
+        .split(',')
+        .map(|ip| {
+            ip.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .with_context(|| format!("Invalid address '{}' in --resolve '{}'", ip, spec))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if host.is_empty() {
+        bail!("Invalid --resolve '{}': missing host", spec);
+    }
+
+    Ok((host.to_lowercase(), ips))
+}
+
+/// Name resolution failure, kept distinct from connect errors
+#[derive(Debug, Clone)]
+pub struct DnsError {
+    pub host: String,
+    pub message: String,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DNS lookup for {} failed: {}", self.host, self.message)
+    }
+}
+
+impl std::error::Error for DnsError {}
+
+struct Lookup {
+    resolved_at: Instant,
+    result: Result<Arc<Vec<IpAddr>>, DnsError>,
+}
+
+/// Lookup counters for the run summary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DnsStats {
+    pub lookups: u64,
+    pub cache_hits: u64,
+    pub failures: u64,
+}
+
+/// Resolver shared by all clients of a downloader: overrides first, then an
+/// in-process cache in front of the system resolver
+///
+/// Concurrent requests for an uncached host wait on a single lookup.
+pub struct CachingResolver {
+    config: DnsConfig,
+    cache: Mutex<HashMap<String, Arc<OnceCell<Lookup>>>>,
+    lookups: AtomicU64,
+    cache_hits: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl CachingResolver {
+    pub fn new(config: DnsConfig) -> Self {
+        Self {
+            config,
+            cache: Mutex::new(HashMap::new()),
+            lookups: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> DnsStats {
+        DnsStats {
+            lookups: self.lookups.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+
+    fn ttl(&self, lookup: &Lookup) -> Duration {
+        match lookup.result {
+            Ok(_) => Duration::from_secs(self.config.cache_ttl_secs),
+            Err(_) => Duration::from_secs(self.config.negative_ttl_secs),
+        }
+    }
+
+    /// Cache slot for a host, replacing it if its lookup has expired
+    fn slot(&self, host: &str) -> Arc<OnceCell<Lookup>> {
+        let mut cache = self.cache.lock().expect("DNS cache poisoned");
+        if let Some(cell) = cache.get(host) {
+            let fresh = cell
+                .get()
+                .is_none_or(|lookup| lookup.resolved_at.elapsed() < self.ttl(lookup));
+            if fresh {
+                return Arc::clone(cell);
+            }
+        }
+
+        let cell = Arc::new(OnceCell::new());
+        cache.insert(host.to_string(), Arc::clone(&cell));
+        cell
+    }
+
+    /// Addresses of `host`, from an override, the cache or a fresh lookup
+    pub async fn lookup(&self, host: &str) -> Result<Arc<Vec<IpAddr>>, DnsError> {
+        let host = host.to_lowercase();
+        if let Some(ips) = self.config.overrides.get(&host) {
+            return Ok(Arc::new(ips.clone()));
+        }
+
+        let slot = self.slot(&host);
+        let mut looked_up = false;
+        let lookup = slot
+            .get_or_init(|| {
+                looked_up = true;
+                system_lookup(&host)
+            })
+            .await;
+
+        if looked_up {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            if lookup.result.is_err() {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        } else {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let result = lookup.result.clone();
+        if self.config.cache_ttl_secs == 0 {
+            self.cache.lock().expect("DNS cache poisoned").remove(&host);
+        }
+        result
+    }
+}
+
+async fn system_lookup(host: &str) -> Lookup {
+    let result = match tokio::net::lookup_host((host, 0)).await {
+        Ok(addrs) => {
+            let mut ips: Vec<IpAddr> = Vec::new();
+            for addr in addrs {
+                if !ips.contains(&addr.ip()) {
+                    ips.push(addr.ip());
+                }
+            }
+            if ips.is_empty() {
+                Err(DnsError {
+                    host: host.to_string(),
+                    message: "no addresses found".to_string(),
+                })
+            } else {
+                Ok(Arc::new(ips))
+            }
+        }
+        Err(e) => Err(DnsError {
+            host: host.to_string(),
+            message: e.to_string(),
+        }),
+    };
+
+    Lookup {
+        resolved_at: Instant::now(),
+        result,
+    }
+}
+
+/// Lets several clients (one per proxy) share one resolver and cache
+#[derive(Clone)]
+pub struct SharedResolver(pub Arc<CachingResolver>);
+
+impl Resolve for SharedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = Arc::clone(&self.0);
+        Box::pin(async move {
+            let ips = resolver.lookup(name.as_str()).await?;
+            // Port 0 is replaced by the URL's port when connecting
+            let addrs: Vec<SocketAddr> = ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_override() {
+        let (host, ips) = parse_override("Mirror.Example.com:10.0.0.5").unwrap();
+        assert_eq!(host, "mirror.example.com");
+        assert_eq!(ips, vec!["10.0.0.5".parse::<IpAddr>().unwrap()]);
+
+        let (_, ips) = parse_override("example.com:443:127.0.0.1,[::1]").unwrap();
+        assert_eq!(ips.len(), 2);
+
+        assert!(parse_override("example.com").is_err());
+        assert!(parse_override("example.com:not-an-ip").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_cache() {
+        let mut overrides = HashMap::new();
+        overrides.insert("staging.test".to_string(), vec!["127.0.0.2".parse().unwrap()]);
+        let resolver = CachingResolver::new(DnsConfig {
+            overrides,
+            ..Default::default()
+        });
+
+        let ips = resolver.lookup("STAGING.test").await.unwrap();
+        assert_eq!(ips.as_slice(), &["127.0.0.2".parse::<IpAddr>().unwrap()]);
+
+        resolver.lookup("localhost").await.unwrap();
+        resolver.lookup("localhost").await.unwrap();
+        let stats = resolver.stats();
+        assert_eq!(stats.lookups, 1);
+        assert_eq!(stats.cache_hits, 1);
+    }
+}
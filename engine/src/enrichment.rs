@@ -11,6 +11,8 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use url::Url;
 
+use crate::cassette::Cassette;
+
 /// Configuration for enrichment APIs
 #[derive(Debug, Clone, Deserialize)]
 pub struct EnrichmentConfig {
@@ -230,7 +232,12 @@ impl CacheManager {
 /// Trait for enrichment providers
 #[async_trait]
 trait EnrichmentProvider: Send + Sync {
-    async fn enrich(&self, url: &str, client: &Client) -> Result<Option<ScoreAdjustment>>;
+    async fn enrich(
+        &self,
+        url: &str,
+        client: &Client,
+        cassette: &Cassette,
+    ) -> Result<Option<ScoreAdjustment>>;
     #[allow(dead_code)]
     fn name(&self) -> &str;
 }
@@ -251,7 +258,12 @@ impl GoogleTrendsProvider {
 
 #[async_trait]
 impl EnrichmentProvider for GoogleTrendsProvider {
-    async fn enrich(&self, url: &str, _client: &Client) -> Result<Option<ScoreAdjustment>> {
+    async fn enrich(
+        &self,
+        url: &str,
+        _client: &Client,
+        _cassette: &Cassette,
+    ) -> Result<Option<ScoreAdjustment>> {
         // Extract domain keywords for trend checking
         let domain = self.extract_domain(url)?;
         
@@ -292,9 +304,9 @@ struct ShopifyDetectionProvider {
 
 impl ShopifyDetectionProvider {
     /// Fetch HTML content with timeout
-    async fn fetch_html(&self, url: &str, client: &Client) -> Option<String> {
+    async fn fetch_html(&self, url: &str, client: &Client, cassette: &Cassette) -> Option<String> {
         let timeout = std::time::Duration::from_secs(self.config.timeout_seconds);
-        tokio::time::timeout(timeout, client.get(url).send())
+        tokio::time::timeout(timeout, cassette.send(client.get(url)))
             .await
             .ok()?
             .ok()?
@@ -306,9 +318,14 @@ impl ShopifyDetectionProvider {
 
 #[async_trait]
 impl EnrichmentProvider for ShopifyDetectionProvider {
-    async fn enrich(&self, url: &str, client: &Client) -> Result<Option<ScoreAdjustment>> {
+    async fn enrich(
+        &self,
+        url: &str,
+        client: &Client,
+        cassette: &Cassette,
+    ) -> Result<Option<ScoreAdjustment>> {
         // Fetch HTML and check for Shopify signatures
-        if let Some(html) = self.fetch_html(url, client).await {
+        if let Some(html) = self.fetch_html(url, client, cassette).await {
             // Check for Shopify signatures in HTML
             let shopify_patterns = [
                 "cdn.shopify.com",
@@ -353,7 +370,12 @@ impl WhoisProvider {
 
 #[async_trait]
 impl EnrichmentProvider for WhoisProvider {
-    async fn enrich(&self, url: &str, _client: &Client) -> Result<Option<ScoreAdjustment>> {
+    async fn enrich(
+        &self,
+        url: &str,
+        _client: &Client,
+        _cassette: &Cassette,
+    ) -> Result<Option<ScoreAdjustment>> {
         let domain = self.extract_domain(url)?;
 
         // Use tokio::process to call whois command
@@ -426,6 +448,7 @@ impl WhoisProvider {
 pub struct EnrichmentEngine {
     config: EnrichmentConfig,
     client: Client,
+    cassette: Arc<Cassette>,
     cache: CacheManager,
     rate_limiter: RateLimiter,
 }
@@ -445,17 +468,24 @@ impl EnrichmentEngine {
         Ok(Self {
             config,
             client,
+            cassette: Arc::new(Cassette::live()),
             cache,
             rate_limiter: RateLimiter::new(),
         })
     }
 
+    /// Send HTTP requests through a recording or replaying cassette
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
     /// Detect site type from URL and HTML
     async fn detect_site_type(&self, url: &str) -> Result<SiteType> {
         let timeout = std::time::Duration::from_secs(5);
         let response = tokio::time::timeout(
             timeout,
-            self.client.get(url).send()
+            self.cassette.send(self.client.get(url))
         ).await;
 
         match response {
@@ -499,52 +529,56 @@ impl EnrichmentEngine {
         let site_type = self.detect_site_type(url).await.unwrap_or(SiteType::Unknown);
 
         // Google Trends
-        if self.config.apis.google_trends_enabled {
-            if self.rate_limiter.check_and_record(
-                "google_trends",
-                self.config.google_trends.rate_limit_per_hour
-            ).await.is_ok() {
-                let provider = GoogleTrendsProvider {
-                    config: self.config.google_trends.clone(),
-                };
-                
-                if let Ok(Some(adj)) = provider.enrich(url, &self.client).await {
-                    enrichment_data.is_trending = Some(true);
-                    adjustments.push(adj);
-                }
+        if self.config.apis.google_trends_enabled
+            && self
+                .rate_limiter
+                .check_and_record("google_trends", self.config.google_trends.rate_limit_per_hour)
+                .await
+                .is_ok()
+        {
+            let provider = GoogleTrendsProvider {
+                config: self.config.google_trends.clone(),
+            };
+            
+            if let Ok(Some(adj)) = provider.enrich(url, &self.client, &self.cassette).await {
+                enrichment_data.is_trending = Some(true);
+                adjustments.push(adj);
             }
         }
 
         // Shopify Detection
-        if self.config.apis.shopify_detection_enabled {
-            if self.rate_limiter.check_and_record(
-                "shopify",
-                self.config.shopify.rate_limit_per_hour
-            ).await.is_ok() {
-                let provider = ShopifyDetectionProvider {
-                    config: self.config.shopify.clone(),
-                };
-                
-                if let Ok(Some(adj)) = provider.enrich(url, &self.client).await {
-                    enrichment_data.is_shopify = Some(true);
-                    adjustments.push(adj);
-                }
+        if self.config.apis.shopify_detection_enabled
+            && self
+                .rate_limiter
+                .check_and_record("shopify", self.config.shopify.rate_limit_per_hour)
+                .await
+                .is_ok()
+        {
+            let provider = ShopifyDetectionProvider {
+                config: self.config.shopify.clone(),
+            };
+            
+            if let Ok(Some(adj)) = provider.enrich(url, &self.client, &self.cassette).await {
+                enrichment_data.is_shopify = Some(true);
+                adjustments.push(adj);
             }
         }
 
-        // Whois
-        if self.config.apis.whois_enabled {
-            if self.rate_limiter.check_and_record(
-                "whois",
-                self.config.whois.rate_limit_per_hour
-            ).await.is_ok() {
-                let provider = WhoisProvider {
-                    config: self.config.whois.clone(),
-                };
-                
-                if let Ok(Some(adj)) = provider.enrich(url, &self.client).await {
-                    adjustments.push(adj);
-                }
+        // Whois (a local command, not HTTP, so it can't be replayed)
+        if self.config.apis.whois_enabled
+            && !self.cassette.is_replay()
+            && self
+                .rate_limiter
+                .check_and_record("whois", self.config.whois.rate_limit_per_hour)
+                .await
+                .is_ok()
+        {
+            let provider = WhoisProvider {
+                config: self.config.whois.clone(),
+            };
+            
+            if let Ok(Some(adj)) = provider.enrich(url, &self.client, &self.cassette).await {
+                adjustments.push(adj);
             }
         }
 
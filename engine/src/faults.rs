@@ -0,0 +1,246 @@
+use anyhow::{bail, Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::retry::{AttemptFailure, FailureKind};
+
+/// Rates (0.0-1.0) at which attempts are sabotaged, for testing retries,
+/// breakers and partial saves
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultConfig {
+    /// Attempt hangs for the request timeout, then fails as a timeout
+    pub timeout_rate: f64,
+    /// Attempt fails at once with a connection reset
+    pub reset_rate: f64,
+    /// Response body is cut short and fails to read
+    pub truncate_rate: f64,
+    /// Attempt is answered with 429 Too Many Requests
+    pub rate_limit_rate: f64,
+    /// Attempt is answered with 503 Service Unavailable
+    pub unavailable_rate: f64,
+    /// Same seed and URLs give the same faults, whatever the scheduling order
+    pub seed: u64,
+}
+
+impl FaultConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.timeout_rate + self.reset_rate + self.truncate_rate + self.rate_limit_rate
+            + self.unavailable_rate
+            > 0.0
+    }
+}
+
+impl FromStr for FaultConfig {
+    type Err = anyhow::Error;
+
+    /// Comma-separated `fault=rate` pairs, e.g. "timeout=0.05,reset=0.02,429=0.1";
+    /// faults are timeout, reset, truncate, 429 and 503
+    fn from_str(s: &str) -> Result<Self> {
+        let mut config = FaultConfig::default();
+        for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((fault, rate)) = pair.split_once('=') else {
+                bail!("Invalid fault '{}': expected fault=rate", pair);
+            };
+            let rate: f64 = rate
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid rate in fault '{}'", pair))?;
+            if !(0.0..=1.0).contains(&rate) {
+                bail!("Fault rate must be between 0 and 1: '{}'", pair);
+            }
+
+            match fault.trim() {
+                "timeout" => config.timeout_rate = rate,
+                "reset" => config.reset_rate = rate,
+                "truncate" => config.truncate_rate = rate,
+                "429" => config.rate_limit_rate = rate,
+                "503" => config.unavailable_rate = rate,
+                other => bail!(
+                    "Unknown fault '{}' (expected timeout, reset, truncate, 429 or 503)",
+                    other
+                ),
+            }
+        }
+
+        if config.timeout_rate + config.reset_rate + config.rate_limit_rate + config.unavailable_rate
+            > 1.0
+        {
+            bail!("Request fault rates (timeout, reset, 429, 503) add up to more than 1");
+        }
+        Ok(config)
+    }
+}
+
+/// Fault injected into one attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Fault {
+    Timeout,
+    ConnectionReset,
+    TruncatedBody,
+    RateLimited,
+    Unavailable,
+}
+
+impl Fault {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Fault::Timeout => "timeout",
+            Fault::ConnectionReset => "reset",
+            Fault::TruncatedBody => "truncate",
+            Fault::RateLimited => "429",
+            Fault::Unavailable => "503",
+        }
+    }
+
+    /// Whether the fault replaces the request (as opposed to spoiling its body)
+    pub fn is_request_fault(self) -> bool {
+        self != Fault::TruncatedBody
+    }
+
+    /// Play out a request-level fault: wait like the real failure would, then
+    /// return the failure the downloader would have seen
+    pub async fn fail_request(self, timeout: Duration) -> AttemptFailure {
+        match self {
+            Fault::Timeout => {
+                tokio::time::sleep(timeout).await;
+                AttemptFailure::new(FailureKind::Timeout, "Injected fault: request timed out")
+            }
+            Fault::ConnectionReset => AttemptFailure::new(
+                FailureKind::ConnectionReset,
+                "Injected fault: connection reset by peer",
+            ),
+            Fault::RateLimited => AttemptFailure::from_status(429, &HeaderMap::new()),
+            Fault::Unavailable => AttemptFailure::from_status(503, &HeaderMap::new()),
+            Fault::TruncatedBody => AttemptFailure::new(
+                FailureKind::ConnectionReset,
+                "Injected fault: response body truncated",
+            ),
+        }
+    }
+}
+
+/// Decides which attempts get a fault
+///
+/// Each attempt rolls with an RNG seeded from the seed, the URL and the
+/// attempt's position for that URL, so retries see fresh rolls while runs
+/// with the same seed see the same faults.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    config: FaultConfig,
+    attempts: Mutex<HashMap<String, u64>>,
+    injected: Mutex<BTreeMap<Fault, u64>>,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        Self {
+            config,
+            attempts: Mutex::new(HashMap::new()),
+            injected: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_enabled()
+    }
+
+    /// Fault for the next attempt at `url`, if any
+    pub fn next_fault(&self, url: &str) -> Option<Fault> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let attempt = {
+            let mut attempts = self.attempts.lock().expect("Fault attempts poisoned");
+            let counter = attempts.entry(url.to_string()).or_insert(0);
+            *counter += 1;
+            *counter
+        };
+
+        // A stable hash, so a seed means the same faults across builds and platforms
+        let digest = Sha256::digest(format!("{}:{}:{}", self.config.seed, attempt, url));
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
+        let mut rng = StdRng::seed_from_u64(u64::from_le_bytes(seed));
+
+        let roll: f64 = rng.gen();
+        let request_faults = [
+            (Fault::Timeout, self.config.timeout_rate),
+            (Fault::ConnectionReset, self.config.reset_rate),
+            (Fault::RateLimited, self.config.rate_limit_rate),
+            (Fault::Unavailable, self.config.unavailable_rate),
+        ];
+        let mut threshold = 0.0;
+        let mut fault = request_faults.into_iter().find_map(|(fault, rate)| {
+            threshold += rate;
+            (roll < threshold).then_some(fault)
+        });
+        if fault.is_none() && rng.gen_bool(self.config.truncate_rate.clamp(0.0, 1.0)) {
+            fault = Some(Fault::TruncatedBody);
+        }
+
+        if let Some(fault) = fault {
+            *self.injected.lock().expect("Fault counts poisoned").entry(fault).or_insert(0) += 1;
+        }
+        fault
+    }
+
+    /// Faults injected so far, per fault
+    pub fn injected(&self) -> BTreeMap<&'static str, u64> {
+        self.injected
+            .lock()
+            .expect("Fault counts poisoned")
+            .iter()
+            .map(|(fault, count)| (fault.as_str(), *count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fault_spec() {
+        let config: FaultConfig = "timeout=0.05, reset=0.02,truncate=0.1,429=0.2,503=0.01"
+            .parse()
+            .unwrap();
+        assert_eq!(config.rate_limit_rate, 0.2);
+        assert_eq!(config.truncate_rate, 0.1);
+        assert!(config.is_enabled());
+
+        assert!("timeout=2".parse::<FaultConfig>().is_err());
+        assert!("slow=0.1".parse::<FaultConfig>().is_err());
+        assert!("429=0.6,503=0.6".parse::<FaultConfig>().is_err());
+    }
+
+    #[test]
+    fn test_faults_are_reproducible_and_near_their_rates() {
+        let config = FaultConfig {
+            seed: 7,
+            ..FaultConfig::from_str("reset=0.2,503=0.1").unwrap()
+        };
+        let urls: Vec<String> = (0..2000).map(|i| format!("https://site{}.test/", i)).collect();
+
+        let run = || {
+            let injector = FaultInjector::new(config.clone());
+            // Reverse order: scheduling must not change the outcome
+            let faults: Vec<Option<Fault>> =
+                urls.iter().rev().map(|url| injector.next_fault(url)).collect();
+            (faults, injector.injected())
+        };
+        let (first, counts) = run();
+        let (second, _) = run();
+        assert_eq!(first, second);
+
+        assert!((300..500).contains(&counts["reset"]), "{:?}", counts);
+        assert!((120..280).contains(&counts["503"]), "{:?}", counts);
+        assert!(!counts.contains_key("timeout"));
+    }
+}
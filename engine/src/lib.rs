@@ -1,3 +1,27 @@
+pub mod adaptive;
+pub mod benchmark;
+pub mod blobs;
+pub mod breaker;
+pub mod cassette;
+pub mod checkpoint;
+pub mod dataset;
+pub mod db;
+pub mod decode;
+pub mod dns;
 pub mod enrichment;
+pub mod faults;
+pub mod parallel;
+pub mod policy;
+pub mod politeness;
+pub mod profiles;
+pub mod progress;
+pub mod proxy;
+pub mod retry;
+pub mod schedule;
+pub mod shutdown;
+pub mod storage;
+pub mod synthetic;
 
 pub use enrichment::{enrich_score, EnrichmentConfig, EnrichedScore, EnrichmentEngine};
+pub use parallel::{DownloadResult, ParallelConfig, ParallelDownloader};
+pub use storage::{ParquetStorage, ParsedRecord, ReadOptions};
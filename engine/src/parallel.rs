@@ -1,69 +1,295 @@
 use anyhow::{Context, Result};
-use futures::stream::{self, StreamExt};
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::header::{
+    HeaderMap, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, LOCATION, SERVER,
+};
+use reqwest::{redirect, Client, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::adaptive::{AdaptiveConfig, ConcurrencyController, ConcurrencyReport, Sample};
+use crate::breaker::{BreakerConfig, CircuitBreakers, Transition};
+use crate::cassette::Cassette;
+use crate::db::{EventSink, Validators};
+use crate::decode;
+use crate::dns::{CachingResolver, DnsConfig, DnsStats, SharedResolver};
+use crate::faults::{Fault, FaultConfig, FaultInjector};
+use crate::policy;
+use crate::politeness::{self, HostScheduler, PolitenessConfig};
+use crate::profiles::RequestProfiles;
+use crate::proxy::{ProxyConfig, ProxyPool, ProxyTransition};
+use crate::retry::{AttemptFailure, FailureKind, RetryBudget, RetryPolicy};
+use crate::schedule::{self, PrioritizedUrl};
+use crate::shutdown::Shutdown;
+
+const USER_AGENT: &str = "JARVIX/2.0 (Scalable OSINT Engine)";
 
 /// Configuration for parallel downloads
 #[derive(Debug, Clone)]
 pub struct ParallelConfig {
     /// Maximum concurrent downloads
     pub max_concurrent: usize,
+    /// Tune concurrency between a minimum and `max_concurrent` from observed latency and errors
+    pub adaptive: AdaptiveConfig,
     /// Timeout per request in seconds
     pub timeout_secs: u64,
-    /// Maximum retries per URL
-    pub max_retries: usize,
+    /// Maximum redirects followed per request
+    pub max_redirects: usize,
+    /// Largest (decompressed) body accepted, in bytes (None = unlimited)
+    pub max_body_bytes: Option<u64>,
+    /// Accepted media types, e.g. "text/html" or "text/*" (empty = any)
+    pub allowed_content_types: Vec<String>,
+    /// Which failures are retried, backoff and run-wide retry budget
+    pub retry: RetryPolicy,
+    /// Per-host concurrency and request spacing
+    pub politeness: PolitenessConfig,
+    /// Per-host circuit breaker
+    pub breaker: BreakerConfig,
+    /// `--resolve` overrides and DNS cache lifetimes
+    pub dns: DnsConfig,
+    /// Per-domain headers, cookies, auth and user agent
+    pub profiles: RequestProfiles,
+    /// Proxies to send requests through (none = direct connections)
+    pub proxy: ProxyConfig,
+    /// Record or replay every HTTP exchange (live by default)
+    pub cassette: Arc<Cassette>,
+    /// Deliberately sabotage attempts at these rates (off by default)
+    pub faults: FaultConfig,
+    /// Where to log run events such as breaker trips (None = tracing only)
+    pub events: Option<EventSink>,
 }
 
 impl Default for ParallelConfig {
     fn default() -> Self {
         Self {
             max_concurrent: 100,
+            adaptive: AdaptiveConfig::default(),
             timeout_secs: 30,
-            max_retries: 3,
+            max_redirects: 10,
+            max_body_bytes: Some(10 * 1024 * 1024),
+            allowed_content_types: default_content_types(),
+            retry: RetryPolicy::default(),
+            politeness: PolitenessConfig::default(),
+            breaker: BreakerConfig::default(),
+            dns: DnsConfig::default(),
+            profiles: RequestProfiles::default(),
+            proxy: ProxyConfig::default(),
+            cassette: Arc::new(Cassette::live()),
+            faults: FaultConfig::default(),
+            events: None,
         }
     }
 }
 
 /// Result of a download operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DownloadResult {
     pub url: String,
     pub success: bool,
     pub content: Option<String>,
     pub status_code: Option<u16>,
     pub error: Option<String>,
+    /// Class of the final failure, when `success` is false
+    pub error_kind: Option<FailureKind>,
     pub duration_ms: u64,
+    /// Number of attempts made (1 = no retries)
+    pub attempts: u32,
+    /// Why each failed attempt failed, in order
+    pub failures: Vec<AttemptFailure>,
+    /// `ETag` header of the response
+    pub etag: Option<String>,
+    /// `Last-Modified` header of the response
+    pub last_modified: Option<String>,
+    /// Run whose raw file holds the body when the server answered 304 Not Modified
+    pub content_run_id: Option<String>,
+    /// URL that produced the final response, after redirects
+    pub final_url: Option<String>,
+    /// URLs that answered with a redirect, in the order they were visited
+    pub redirect_chain: Vec<String>,
+    /// `Content-Type` header of the response
+    pub content_type: Option<String>,
+    /// `Server` header of the response
+    pub server: Option<String>,
+    /// `Cache-Control` header of the response
+    pub cache_control: Option<String>,
+    /// All response headers (repeated headers joined with ", ")
+    pub headers: BTreeMap<String, String>,
+    /// Size of the response body in bytes, after undoing any `Content-Encoding`
+    pub content_length: Option<u64>,
+    /// Hex SHA-256 of the response body after undoing any `Content-Encoding`,
+    /// before charset decoding
+    pub body_sha256: Option<String>,
+    /// Character encoding the body was decoded from, e.g. "Shift_JIS"
+    pub encoding: Option<String>,
+    /// When the first attempt was started
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// IP address of the server that sent the final response
+    pub remote_ip: Option<String>,
+    /// HTTP version of the final response, e.g. "HTTP/1.1"
+    pub http_version: Option<String>,
+    /// Proxy the (last) attempt went through, without credentials
+    pub proxy: Option<String>,
+    /// SHA-256 of the decoded text, when `content` was moved to the blob store
+    pub content_blob: Option<String>,
+}
+
+/// Per-URL progress of a download stream, for live progress views
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    /// The URL got its host and global slots and is being fetched
+    Started { url: String },
+    /// The URL has its final result (failed URLs included)
+    Finished {
+        url: String,
+        success: bool,
+        status_code: Option<u16>,
+        error_kind: Option<FailureKind>,
+    },
+}
+
+impl DownloadResult {
+    /// Placeholder for a URL the run deadline left unfetched
+    pub fn skipped_deadline(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            error: Some("Not fetched before the run deadline".to_string()),
+            error_kind: Some(FailureKind::SkippedDeadline),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the body was not re-downloaded because it is unchanged
+    pub fn is_not_modified(&self) -> bool {
+        self.status_code == Some(StatusCode::NOT_MODIFIED.as_u16())
+    }
+
+    /// Validators to store for this result, if it has a body to point to
+    ///
+    /// `run_id` is the run this result is being stored in.
+    pub fn validators(&self, run_id: &str) -> Option<Validators> {
+        if !self.success || (self.etag.is_none() && self.last_modified.is_none()) {
+            return None;
+        }
+
+        let content_run_id = match &self.content_run_id {
+            Some(previous) if self.is_not_modified() => previous.clone(),
+            _ => run_id.to_string(),
+        };
+
+        Some(Validators {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+            content_run_id,
+        })
+    }
 }
 
 /// Parallel downloader with worker pool
 pub struct ParallelDownloader {
     client: Client,
     config: ParallelConfig,
-    semaphore: Arc<Semaphore>,
+    proxies: ProxyPool,
+    resolver: Arc<CachingResolver>,
+    concurrency: ConcurrencyController,
+    shutdown: Shutdown,
+    scheduler: Arc<HostScheduler>,
+    breakers: CircuitBreakers,
+    validators: HashMap<String, Validators>,
+    faults: FaultInjector,
+    events: Option<mpsc::UnboundedSender<DownloadEvent>>,
 }
 
 impl ParallelDownloader {
     /// Create a new parallel downloader
     pub fn new(config: ParallelConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.timeout_secs))
-            .gzip(true)
-            .user_agent("JARVIX/2.0 (Scalable OSINT Engine)")
-            .build()
-            .context("Failed to build HTTP client")?;
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let resolver = Arc::new(CachingResolver::new(config.dns.clone()));
+        let builder = || {
+            Client::builder()
+                .timeout(timeout)
+                .dns_resolver(Arc::new(SharedResolver(Arc::clone(&resolver))))
+                .gzip(true)
+                .brotli(true)
+                .zstd(true)
+                .deflate(true)
+                .user_agent(USER_AGENT)
+                // Redirects are followed by hand to record the chain
+                .redirect(redirect::Policy::none())
+        };
+        let client = builder().build().context("Failed to build HTTP client")?;
+        let proxies = ProxyPool::new(config.proxy.clone(), builder)?;
+        if !proxies.is_empty() {
+            info!("Using {} proxies ({:?})", proxies.len(), config.proxy.rotation);
+        }
 
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrent));
+        let concurrency =
+            ConcurrencyController::new(config.max_concurrent, config.adaptive.clone());
+        let scheduler = Arc::new(HostScheduler::new(config.politeness.clone()));
+        let breakers = CircuitBreakers::new(config.breaker.clone());
 
         Ok(Self {
             client,
+            proxies,
+            resolver,
+            faults: FaultInjector::new(config.faults.clone()),
             config,
-            semaphore,
+            concurrency,
+            shutdown: Shutdown::new(),
+            scheduler,
+            breakers,
+            validators: HashMap::new(),
+            events: None,
         })
     }
 
+    /// Send a `DownloadEvent` for every URL started and finished
+    pub fn with_progress(mut self, events: mpsc::UnboundedSender<DownloadEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn emit(&self, event: DownloadEvent) {
+        if let Some(events) = &self.events {
+            // The progress view going away must not affect downloads
+            let _ = events.send(event);
+        }
+    }
+
+    /// Use validators from previous runs to send conditional requests
+    pub fn with_validators(mut self, validators: HashMap<String, Validators>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Stop starting downloads once `shutdown` is triggered
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Faults injected so far, per fault ("timeout", "reset", "truncate", "429", "503")
+    pub fn injected_faults(&self) -> BTreeMap<&'static str, u64> {
+        self.faults.injected()
+    }
+
+    /// DNS lookups and cache hits so far
+    pub fn dns_stats(&self) -> DnsStats {
+        self.resolver.stats()
+    }
+
+    /// Concurrency limits and requests in flight so far for this downloader
+    pub fn concurrency_report(&self) -> ConcurrencyReport {
+        self.concurrency.report()
+    }
+
     /// Download URLs in parallel with worker pool
     pub async fn download_all(&self, urls: Vec<String>) -> Vec<DownloadResult> {
         let total = urls.len();
@@ -71,25 +297,7 @@ impl ParallelDownloader {
               total, self.config.max_concurrent);
 
         let start_time = Instant::now();
-
-        let results: Vec<DownloadResult> = stream::iter(urls)
-            .map(|url| {
-                let client = self.client.clone();
-                let semaphore = Arc::clone(&self.semaphore);
-                let max_retries = self.config.max_retries;
-                
-                async move {
-                    // Acquire semaphore permit to limit concurrency
-                    let _permit = semaphore.acquire().await.expect("Semaphore closed");
-                    
-                    let result = Self::download_with_retry(&client, &url, max_retries).await;
-                    debug!("Completed: {} - Success: {}", url, result.success);
-                    result
-                }
-            })
-            .buffer_unordered(self.config.max_concurrent)
-            .collect()
-            .await;
+        let results: Vec<DownloadResult> = self.download_stream(urls).collect().await;
 
         let duration = start_time.elapsed();
         let success_count = results.iter().filter(|r| r.success).count();
@@ -102,106 +310,634 @@ impl ParallelDownloader {
             duration.as_secs_f64(),
             avg_time_ms
         );
+        if self.config.adaptive.enabled {
+            let report = self.concurrency_report();
+            info!(
+                "Concurrency: {} peak, {:.1} avg in flight (limit {}-{}, avg {:.1}, final {}, {} adjustments)",
+                report.peak_in_flight,
+                report.average_in_flight,
+                report.min,
+                report.max,
+                report.average,
+                report.last,
+                report.adjustments
+            );
+        }
 
         results
     }
 
-    /// Download a single URL with retry logic
+    /// Download URLs in parallel, yielding each result as soon as it finishes
+    ///
+    /// At most `max_concurrent` downloads (and their bodies) are held at once,
+    /// so memory depends on concurrency rather than on the number of URLs.
+    /// Once shutdown is triggered no new downloads are started and URLs that
+    /// were still waiting for a slot are left out of the stream.
+    pub fn download_stream(&self, urls: Vec<String>) -> impl Stream<Item = DownloadResult> + '_ {
+        self.download_stream_by_priority(urls.into_iter().map(PrioritizedUrl::from).collect())
+    }
+
+    /// Like `download_stream`, starting higher-priority URLs first
+    pub fn download_stream_by_priority(
+        &self,
+        urls: Vec<PrioritizedUrl>,
+    ) -> impl Stream<Item = DownloadResult> + '_ {
+        let budget = Arc::new(RetryBudget::new(self.config.retry.retry_budget));
+
+        // Within a priority, spread hosts across the worker pool so one domain
+        // can't occupy every slot
+        let urls = schedule::order(urls);
+
+        stream::iter(urls)
+            .map(move |url| {
+                let budget = Arc::clone(&budget);
+                async move {
+                    let result = self.fetch(&url, &budget).await;
+                    match &result {
+                        Some(result) => {
+                            debug!("Completed: {} - Success: {}", url, result.success);
+                            self.emit(DownloadEvent::Finished {
+                                url: url.clone(),
+                                success: result.success,
+                                status_code: result.status_code,
+                                error_kind: result.error_kind,
+                            });
+                        }
+                        None => debug!("Not started: {} (shutting down)", url),
+                    }
+                    result
+                }
+            })
+            .buffer_unordered(self.config.max_concurrent)
+            .filter_map(future::ready)
+    }
+
+    /// Schedule and download one URL; None if shutdown came first
+    async fn fetch(&self, url: &str, budget: &RetryBudget) -> Option<DownloadResult> {
+        if self.shutdown.is_triggered() {
+            return None;
+        }
+
+        let host = politeness::host_key(url);
+
+        // Don't queue behind the host's politeness delay if it's known to be down
+        if self.breakers.is_open(&host) {
+            return Some(Self::circuit_open_result(url, &host, 1, Vec::new(), Instant::now()));
+        }
+
+        let permits = async {
+            // Wait for the host's turn before taking a global slot
+            self.scheduler
+                .init_crawl_delay(&host, || {
+                    let user_agent = self.config.profiles.for_host(&host).user_agent();
+                    policy::fetch_crawl_delay(
+                        &self.config.cassette,
+                        self.client_for(&host),
+                        url,
+                        user_agent.unwrap_or(USER_AGENT),
+                    )
+                })
+                .await;
+            let host_permit = self.scheduler.acquire(&host).await;
+
+            // Acquire a global slot under the current concurrency limit
+            let permit = self.concurrency.acquire().await;
+            (host_permit, permit)
+        };
+
+        let (_host_permit, _permit) = tokio::select! {
+            biased;
+            _ = self.shutdown.wait() => return None,
+            permits = permits => permits,
+        };
+
+        self.emit(DownloadEvent::Started {
+            url: url.to_string(),
+        });
+        self.download_with_retry(url, &host, budget).await
+    }
+
+    /// Download a single URL, retrying transient failures per the retry policy
+    ///
+    /// Returns None when shutdown interrupts a backoff, so the URL is fetched again on resume.
     async fn download_with_retry(
-        client: &Client,
+        &self,
         url: &str,
-        max_retries: usize,
-    ) -> DownloadResult {
+        host: &str,
+        budget: &RetryBudget,
+    ) -> Option<DownloadResult> {
+        let policy = &self.config.retry;
         let start = Instant::now();
-        
-        for attempt in 0..=max_retries {
-            if attempt > 0 {
-                warn!("Retry {}/{} for {}", attempt, max_retries, url);
-                tokio::time::sleep(Duration::from_millis(100 * attempt as u64)).await;
+        let fetched_at = Utc::now();
+        let mut failures: Vec<AttemptFailure> = Vec::new();
+        let mut attempt: u32 = 0;
+        let mut proxy: Option<usize>;
+
+        loop {
+            attempt += 1;
+
+            if !self.breakers.try_admit(host) {
+                return Some(Self::circuit_open_result(url, host, attempt, failures, start));
             }
 
-            match Self::download_once(client, url).await {
-                Ok(result) if result.success => {
-                    return result;
-                }
-                Ok(result) if attempt == max_retries => {
-                    return result; // Return failed result after max retries
-                }
-                Err(e) if attempt == max_retries => {
-                    return DownloadResult {
-                        url: url.to_string(),
-                        success: false,
-                        content: None,
-                        status_code: None,
-                        error: Some(e.to_string()),
-                        duration_ms: start.elapsed().as_millis() as u64,
-                    };
+            // Pick per attempt so a retry can go out through a different proxy
+            proxy = self.proxies.pick(host);
+            let attempt_start = Instant::now();
+            let outcome = self.download_once(url, proxy).await;
+            self.concurrency.record(Sample {
+                latency: attempt_start.elapsed(),
+                overloaded: outcome.as_ref().is_err_and(|f| f.kind.is_transient()),
+            });
+            if let Some(proxy) = proxy {
+                let kind = outcome.as_ref().err().map(|f| f.kind);
+                self.record_proxy(proxy, kind);
+            }
+
+            let mut failure = match outcome {
+                Ok(mut result) => {
+                    self.record_breaker(host, url, self.breakers.record_success(host));
+                    result.attempts = attempt;
+                    result.failures = failures;
+                    result.duration_ms = start.elapsed().as_millis() as u64;
+                    result.proxy = proxy.map(|p| self.proxies.label(p).to_string());
+                    return Some(result);
                 }
-                _ => continue, // Retry
+                Err(failure) => failure,
+            };
+            failure.attempt = attempt;
+
+            if failure.kind.is_transient() {
+                self.record_breaker(host, url, self.breakers.record_failure(host));
+            } else if failure.status_code.is_some() {
+                // The host answered, it's just not a page we can use
+                self.record_breaker(host, url, self.breakers.record_success(host));
+            } else {
+                self.breakers.release_probe(host);
+            }
+
+            let retries_left = (attempt as usize) <= policy.max_retries;
+            let delay = policy.delay_after(attempt, &failure);
+            if failure.kind.is_transient() && retries_left && delay.is_none() {
+                debug!(
+                    "Giving up on {}: Retry-After {:?} exceeds {}s",
+                    url, failure.retry_after, policy.max_retry_after_secs
+                );
+            }
+            let should_retry = failure.kind.is_transient()
+                && retries_left
+                && delay.is_some()
+                && budget.try_spend();
+            failures.push(failure);
+
+            let Some(delay) = delay.filter(|_| should_retry) else {
+                break;
+            };
+
+            warn!(
+                "Retry {}/{} for {} in {:?} ({})",
+                attempt,
+                policy.max_retries,
+                url,
+                delay,
+                failures.last().map(|f| f.kind.as_str()).unwrap_or_default()
+            );
+            tokio::select! {
+                biased;
+                _ = self.shutdown.wait() => return None,
+                _ = tokio::time::sleep(delay) => {}
             }
         }
 
-        // Should not reach here
+        let last = failures.last().cloned();
+        Some(DownloadResult {
+            url: url.to_string(),
+            success: false,
+            status_code: last.as_ref().and_then(|f| f.status_code),
+            error_kind: last.as_ref().map(|f| f.kind),
+            error: last.map(|f| f.message),
+            duration_ms: start.elapsed().as_millis() as u64,
+            attempts: attempt,
+            failures,
+            fetched_at: Some(fetched_at),
+            proxy: proxy.map(|p| self.proxies.label(p).to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Fail-fast result for a URL whose host breaker is open
+    fn circuit_open_result(
+        url: &str,
+        host: &str,
+        attempt: u32,
+        mut failures: Vec<AttemptFailure>,
+        start: Instant,
+    ) -> DownloadResult {
+        let message = format!("Circuit open for host {}", host);
+        let mut failure = AttemptFailure::new(FailureKind::CircuitOpen, message.clone());
+        failure.attempt = attempt;
+        failures.push(failure);
+
         DownloadResult {
             url: url.to_string(),
             success: false,
-            content: None,
-            status_code: None,
-            error: Some("Max retries exceeded".to_string()),
+            error: Some(message),
+            error_kind: Some(FailureKind::CircuitOpen),
             duration_ms: start.elapsed().as_millis() as u64,
+            // The short-circuited attempt never reached the network
+            attempts: attempt - 1,
+            failures,
+            ..Default::default()
+        }
+    }
+
+    /// Client for a one-off request to `host` (robots.txt), through the proxy pool if any
+    fn client_for(&self, host: &str) -> &Client {
+        match self.proxies.pick(host) {
+            Some(proxy) => self.proxies.client(proxy),
+            None => &self.client,
+        }
+    }
+
+    /// Track proxy health from an attempt's outcome, logging ejections and recoveries
+    fn record_proxy(&self, proxy: usize, failure: Option<FailureKind>) {
+        // Only failures to get a connection through count against the proxy
+        let proxy_failed = matches!(
+            failure,
+            Some(FailureKind::Connect | FailureKind::Timeout | FailureKind::ConnectionReset)
+        );
+        let transition = if proxy_failed {
+            self.proxies.record_failure(proxy)
+        } else {
+            self.proxies.record_success(proxy)
+        };
+
+        let label = self.proxies.label(proxy);
+        let (event_type, status, message) = match transition {
+            None => return,
+            Some(ProxyTransition::Ejected { failures }) => (
+                "proxy.ejected",
+                "ejected",
+                format!("Proxy {} ejected after {} consecutive failures", label, failures),
+            ),
+            Some(ProxyTransition::Restored) => (
+                "proxy.restored",
+                "healthy",
+                format!("Proxy {} is healthy again", label),
+            ),
+        };
+        warn!("{}", message);
+
+        if let Some(events) = &self.config.events {
+            let metadata =
+                serde_json::json!({"proxy": label, "eject_secs": self.config.proxy.eject_secs})
+                    .to_string();
+            if let Err(e) = events.log(event_type, None, status, &message, Some(&metadata)) {
+                warn!("Failed to log proxy event: {}", e);
+            }
+        }
+    }
+
+    /// Log breaker state changes and write them to the events table
+    fn record_breaker(&self, host: &str, url: &str, transition: Option<Transition>) {
+        let Some(transition) = transition else {
+            return;
+        };
+
+        let cooldown = self.config.breaker.cooldown_secs;
+        let (event_type, status, message) = match transition {
+            Transition::Opened { failures } => {
+                warn!("Circuit opened for {} after {} consecutive failures", host, failures);
+                (
+                    "breaker.opened",
+                    "open",
+                    format!("Circuit opened for {} after {} consecutive failures", host, failures),
+                )
+            }
+            Transition::Reopened => {
+                warn!("Circuit re-opened for {}: probe request failed", host);
+                ("breaker.opened", "open", format!("Circuit re-opened for {}: probe request failed", host))
+            }
+            Transition::Closed => {
+                info!("Circuit closed for {}: probe request succeeded", host);
+                ("breaker.closed", "closed", format!("Circuit closed for {}: probe request succeeded", host))
+            }
+        };
+
+        if let Some(events) = &self.config.events {
+            let metadata = serde_json::json!({"host": host, "cooldown_secs": cooldown}).to_string();
+            if let Err(e) = events.log(event_type, Some(url), status, &message, Some(&metadata)) {
+                warn!("Failed to log breaker event: {}", e);
+            }
         }
     }
 
-    /// Download a single URL once
-    async fn download_once(client: &Client, url: &str) -> Result<DownloadResult> {
+    /// Download a single URL once, following redirects
+    async fn download_once(
+        &self,
+        url: &str,
+        proxy: Option<usize>,
+    ) -> Result<DownloadResult, AttemptFailure> {
+        let client = proxy.map_or(&self.client, |p| self.proxies.client(p));
         let start = Instant::now();
+        let fetched_at = Utc::now();
+        let previous = self.validators.get(url);
 
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .context("Failed to send request")?;
+        let fault = self.faults.next_fault(url);
+        if let Some(fault) = fault.filter(|f| f.is_request_fault()) {
+            debug!("Injecting {} fault for {}", fault.as_str(), url);
+            return Err(fault.fail_request(Duration::from_secs(self.config.timeout_secs)).await);
+        }
+
+        let mut current = Url::parse(url)
+            .map_err(|e| AttemptFailure::new(FailureKind::Other, format!("Invalid URL: {}", e)))?;
+        let mut redirect_chain = Vec::new();
+
+        let mut response = loop {
+            let mut request = client.get(current.clone());
+            // Profiles are matched per hop so credentials never follow a redirect to another host
+            let hop_host = current.host_str().unwrap_or_default();
+            request = self.config.profiles.for_host(hop_host).apply(request, &current);
+            if let Some(v) = previous {
+                if let Some(etag) = &v.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &v.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let response = self
+                .config
+                .cassette
+                .send_with_limit(request, self.config.max_body_bytes)
+                .await
+                .map_err(|e| AttemptFailure::from_http(&e, "Failed to send request"))?;
+
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .filter(|_| response.status().is_redirection());
+
+            let Some(location) = location else {
+                break response;
+            };
+
+            if redirect_chain.len() >= self.config.max_redirects {
+                return Err(AttemptFailure::new(
+                    FailureKind::Other,
+                    format!("Too many redirects (max {})", self.config.max_redirects),
+                ));
+            }
+
+            let next = current.join(location).map_err(|e| {
+                AttemptFailure::new(FailureKind::Other, format!("Invalid redirect location: {}", e))
+            })?;
+            redirect_chain.push(current.to_string());
+            current = next;
+        };
 
         let status = response.status();
         let status_code = status.as_u16();
+        let headers = response.headers();
 
-        if status.is_success() {
-            let content = response
-                .text()
-                .await
-                .context("Failed to read response body")?;
-
-            Ok(DownloadResult {
-                url: url.to_string(),
-                success: true,
-                content: Some(content),
-                status_code: Some(status_code),
-                error: None,
-                duration_ms: start.elapsed().as_millis() as u64,
-            })
-        } else {
-            Ok(DownloadResult {
-                url: url.to_string(),
-                success: false,
-                content: None,
-                status_code: Some(status_code),
-                error: Some(format!("HTTP {}", status_code)),
-                duration_ms: start.elapsed().as_millis() as u64,
-            })
+        let mut result = DownloadResult {
+            url: url.to_string(),
+            status_code: Some(status_code),
+            final_url: Some(current.to_string()),
+            redirect_chain,
+            etag: header_value(headers, ETAG),
+            last_modified: header_value(headers, LAST_MODIFIED),
+            content_type: header_value(headers, CONTENT_TYPE),
+            server: header_value(headers, SERVER),
+            cache_control: header_value(headers, CACHE_CONTROL),
+            headers: collect_headers(headers),
+            fetched_at: Some(fetched_at),
+            remote_ip: response.remote_addr().map(|addr| addr.ip().to_string()),
+            http_version: Some(format!("{:?}", response.version())),
+            ..Default::default()
+        };
+
+        // Unchanged since the run that stored it: link to that body instead
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(v) = previous {
+                result.success = true;
+                result.etag = result.etag.or_else(|| v.etag.clone());
+                result.last_modified = result.last_modified.or_else(|| v.last_modified.clone());
+                result.content_run_id = Some(v.content_run_id.clone());
+                result.duration_ms = start.elapsed().as_millis() as u64;
+                return Ok(result);
+            }
+        }
+
+        if !status.is_success() {
+            return Err(AttemptFailure::from_status(status_code, headers));
+        }
+
+        // Reject unwanted or oversized bodies before downloading them
+        if !content_type_allowed(&self.config.allowed_content_types, result.content_type.as_deref()) {
+            let mut failure = AttemptFailure::new(
+                FailureKind::DisallowedContentType,
+                format!(
+                    "Content type {} not allowed",
+                    result.content_type.as_deref().unwrap_or("unknown")
+                ),
+            );
+            failure.status_code = Some(status_code);
+            return Err(failure);
+        }
+
+        let max_body_bytes = self.config.max_body_bytes;
+        let too_large = |size: u64| {
+            let mut failure = AttemptFailure::new(
+                FailureKind::BodyTooLarge,
+                format!(
+                    "Body of {} bytes exceeds limit of {} bytes",
+                    size,
+                    max_body_bytes.unwrap_or_default()
+                ),
+            );
+            failure.status_code = Some(status_code);
+            failure
+        };
+
+        if let (Some(limit), Some(declared)) = (max_body_bytes, response.content_length()) {
+            if declared > limit {
+                return Err(too_large(declared));
+            }
+        }
+
+        // A truncate fault drops the connection halfway through the body; like
+        // a real mid-body disconnect (an unexpected EOF) it is a connection reset
+        let cut_at = (fault == Some(Fault::TruncatedBody))
+            .then(|| response.content_length().map_or(1, |len| (len / 2).max(1)));
+        let truncated = |received: usize| {
+            debug!("Injecting truncate fault for {}", url);
+            AttemptFailure::new(
+                FailureKind::ConnectionReset,
+                format!("Injected fault: connection closed after {} bytes of body", received),
+            )
+        };
+
+        // Read chunk by chunk so a lying or missing Content-Length can't exhaust memory
+        let mut body: Vec<u8> = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| AttemptFailure::from_reqwest(&e, "Failed to read response body"))?
+        {
+            body.extend_from_slice(&chunk);
+            if let Some(limit) = max_body_bytes {
+                if body.len() as u64 > limit {
+                    return Err(too_large(body.len() as u64));
+                }
+            }
+            if cut_at.is_some_and(|cut_at| body.len() as u64 >= cut_at) {
+                return Err(truncated(body.len()));
+            }
+        }
+        if cut_at.is_some() {
+            return Err(truncated(body.len()));
+        }
+
+        result.success = true;
+        result.content_length = Some(body.len() as u64);
+        result.body_sha256 = Some(format!("{:x}", Sha256::digest(&body)));
+        let decoded = decode::decode_body(&body, result.content_type.as_deref());
+        debug!("{} decoded as {} ({:?})", url, decoded.encoding.name(), decoded.source);
+        result.encoding = Some(decoded.encoding.name().to_string());
+        result.content = Some(decoded.text);
+        result.duration_ms = start.elapsed().as_millis() as u64;
+
+        Ok(result)
+    }
+}
+
+/// Text media types collected by default
+pub fn default_content_types() -> Vec<String> {
+    ["text/*", "application/xhtml+xml", "application/xml", "application/json"]
+        .iter()
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// Whether a `Content-Type` matches the allow list (exact or "type/*")
+///
+/// Responses without a `Content-Type` are let through.
+fn content_type_allowed(allowed: &[String], content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type else {
+        return true;
+    };
+    if allowed.is_empty() {
+        return true;
+    }
+
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    let main_type = media_type.split('/').next().unwrap_or("");
+
+    allowed.iter().any(|pattern| {
+        let pattern = pattern.trim().to_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some("*") => true,
+            Some(prefix) => prefix == main_type,
+            None => pattern == media_type,
         }
+    })
+}
+
+/// Value of a header as a string, if present and valid
+fn header_value(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// All headers by lowercase name; repeated headers are joined with ", "
+fn collect_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut collected: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        collected
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
     }
+    collected
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    /// Serve canned HTTP responses on a local port; `respond` gets the raw request
+    async fn serve<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        serve_bytes(move |request| respond(request).into_bytes()).await
+    }
+
+    /// Like `serve`, for responses with binary bodies
+    async fn serve_bytes<F>(respond: F) -> String
+    where
+        F: Fn(&str) -> Vec<u8> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let respond = Arc::new(respond);
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let respond = Arc::clone(&respond);
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 8192];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let response = respond(&String::from_utf8_lossy(&buf[..n]));
+                    socket.write_all(&response).await.ok();
+                    socket.shutdown().await.ok();
+                });
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn local_config() -> ParallelConfig {
+        ParallelConfig {
+            max_concurrent: 4,
+            timeout_secs: 5,
+            politeness: PolitenessConfig {
+                min_delay_ms: 0,
+                respect_crawl_delay: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
 
     #[tokio::test]
     async fn test_parallel_download() {
         let config = ParallelConfig {
             max_concurrent: 10,
             timeout_secs: 10,
-            max_retries: 1,
+            retry: RetryPolicy {
+                max_retries: 1,
+                ..Default::default()
+            },
+            ..Default::default()
         };
 
         let downloader = ParallelDownloader::new(config).unwrap();
@@ -214,4 +950,572 @@ mod tests {
         let results = downloader.download_all(urls).await;
         assert_eq!(results.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_follows_robots_txt_redirects() {
+        let base = serve(|request| {
+            if request.starts_with("GET /robots.txt ") {
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: /en/robots.txt\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nUser-agent: *\nCrawl-delay: 3\n"
+            }
+            .to_string()
+        })
+        .await;
+
+        // The downloader's client follows no redirects itself
+        let downloader = ParallelDownloader::new(local_config()).unwrap();
+        let delay = policy::fetch_crawl_delay(
+            &downloader.config.cassette,
+            downloader.client_for("127.0.0.1"),
+            &format!("{}/page", base),
+            USER_AGENT,
+        )
+        .await;
+        assert_eq!(delay, Some(Duration::from_secs(3)));
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_fails_fast() {
+        let config = ParallelConfig {
+            max_concurrent: 4,
+            timeout_secs: 2,
+            retry: RetryPolicy {
+                max_retries: 0,
+                ..Default::default()
+            },
+            politeness: PolitenessConfig {
+                max_per_host: 1,
+                min_delay_ms: 0,
+                respect_crawl_delay: false,
+                ..Default::default()
+            },
+            breaker: BreakerConfig {
+                failure_threshold: 2,
+                cooldown_secs: 60,
+            },
+            ..Default::default()
+        };
+
+        let downloader = ParallelDownloader::new(config).unwrap();
+
+        // Nothing listens on port 1, so every request is refused
+        let urls = (0..5).map(|i| format!("http://127.0.0.1:1/{}", i)).collect();
+        let results = downloader.download_all(urls).await;
+
+        let short_circuited = results
+            .iter()
+            .filter(|r| r.failures.iter().any(|f| f.kind == FailureKind::CircuitOpen))
+            .count();
+        assert_eq!(results.len(), 5);
+        assert_eq!(short_circuited, 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_when_retry_after_exceeds_cap() {
+        let base = serve(|_| {
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 120\r\nContent-Length: 0\r\n\r\n".to_string()
+        })
+        .await;
+        let config = ParallelConfig {
+            retry: RetryPolicy {
+                max_retries: 3,
+                max_retry_after_secs: 5,
+                ..Default::default()
+            },
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+
+        let results = downloader.download_all(vec![format!("{}/busy", base)]).await;
+        assert_eq!(results[0].error_kind, Some(FailureKind::RateLimited));
+        assert_eq!(results[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_ending_in_redirect_loop_is_released() {
+        let base = serve(|request| {
+            if request.starts_with("GET /down ") {
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n".to_string()
+            } else if request.starts_with("GET /loop ") {
+                "HTTP/1.1 302 Found\r\nLocation: /loop\r\nContent-Length: 0\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string()
+            }
+        })
+        .await;
+        let config = ParallelConfig {
+            max_redirects: 2,
+            retry: RetryPolicy {
+                max_retries: 0,
+                ..Default::default()
+            },
+            breaker: BreakerConfig {
+                failure_threshold: 1,
+                cooldown_secs: 0,
+            },
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+
+        let down = downloader.download_all(vec![format!("{}/down", base)]).await;
+        assert_eq!(down[0].error_kind, Some(FailureKind::ServerError));
+
+        // The half-open probe neither proves nor disproves the host is healthy
+        let probe = downloader.download_all(vec![format!("{}/loop", base)]).await;
+        assert_eq!(probe[0].error_kind, Some(FailureKind::Other));
+
+        let after = downloader.download_all(vec![format!("{}/ok", base)]).await;
+        assert!(after[0].success, "{:?}", after[0].error);
+    }
+
+    #[tokio::test]
+    async fn test_download_stream_yields_every_url() {
+        let config = ParallelConfig {
+            max_concurrent: 2,
+            timeout_secs: 2,
+            retry: RetryPolicy {
+                max_retries: 0,
+                ..Default::default()
+            },
+            breaker: BreakerConfig {
+                failure_threshold: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let downloader = ParallelDownloader::new(config).unwrap();
+        let urls: Vec<String> = (0..4).map(|i| format!("http://127.0.0.1:1/{}", i)).collect();
+
+        let mut stream = Box::pin(downloader.download_stream(urls));
+        let mut seen = 0;
+        while let Some(result) = stream.next().await {
+            assert!(!result.success);
+            seen += 1;
+        }
+        assert_eq!(seen, 4);
+    }
+
+    /// Fetch `body` served with `Content-Encoding: encoding`
+    async fn fetch_encoded(encoding: &'static str, body: Vec<u8>) -> DownloadResult {
+        let base = serve_bytes(move |_| {
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Encoding: {}\r\nContent-Length: {}\r\n\r\n",
+                encoding,
+                body.len()
+            )
+            .into_bytes();
+            response.extend_from_slice(&body);
+            response
+        })
+        .await;
+        let downloader = ParallelDownloader::new(local_config()).unwrap();
+        downloader.download_all(vec![base]).await.remove(0)
+    }
+
+    const PAGE: &str = "<p>Café, naïve, 東京</p>";
+
+    #[tokio::test]
+    async fn test_decodes_brotli_body() {
+        let mut body = Vec::new();
+        brotli::BrotliCompress(&mut PAGE.as_bytes(), &mut body, &Default::default()).unwrap();
+
+        let result = fetch_encoded("br", body).await;
+        assert_eq!(result.content.as_deref(), Some(PAGE));
+    }
+
+    #[tokio::test]
+    async fn test_decodes_zstd_body() {
+        let body = zstd::encode_all(PAGE.as_bytes(), 3).unwrap();
+
+        let result = fetch_encoded("zstd", body).await;
+        assert_eq!(result.content.as_deref(), Some(PAGE));
+    }
+
+    #[tokio::test]
+    async fn test_decodes_deflate_body() {
+        use std::io::Write;
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(PAGE.as_bytes()).unwrap();
+
+        let result = fetch_encoded("deflate", encoder.finish().unwrap()).await;
+        assert_eq!(result.content.as_deref(), Some(PAGE));
+        assert_eq!(result.content_length, Some(PAGE.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_urls_are_fetched_first() {
+        let base = serve(|_| "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string()).await;
+        let config = ParallelConfig {
+            max_concurrent: 1,
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+
+        let urls = vec![
+            PrioritizedUrl { url: format!("{}/low", base), priority: -1 },
+            PrioritizedUrl { url: format!("{}/normal", base), priority: 0 },
+            PrioritizedUrl { url: format!("{}/high", base), priority: 10 },
+        ];
+        let order: Vec<String> = downloader
+            .download_stream_by_priority(urls)
+            .map(|result| result.url)
+            .collect()
+            .await;
+
+        assert_eq!(
+            order,
+            vec![format!("{}/high", base), format!("{}/normal", base), format!("{}/low", base)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_links_previous_content() {
+        let base = serve(|request| {
+            if request.to_lowercase().contains("if-none-match: \"v1\"") {
+                "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 4\r\nConnection: close\r\n\r\nbody".to_string()
+            }
+        })
+        .await;
+
+        let cached = format!("{}/cached", base);
+        let fresh = format!("{}/fresh", base);
+        let validators = HashMap::from([(
+            cached.clone(),
+            Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: None,
+                content_run_id: "week_1".to_string(),
+            },
+        )]);
+
+        let downloader = ParallelDownloader::new(local_config())
+            .unwrap()
+            .with_validators(validators);
+        let results = downloader.download_all(vec![cached.clone(), fresh.clone()]).await;
+
+        let not_modified = results.iter().find(|r| r.url == cached).unwrap();
+        assert!(not_modified.success);
+        assert!(not_modified.is_not_modified());
+        assert_eq!(not_modified.content, None);
+        assert_eq!(not_modified.validators("week_2").unwrap().content_run_id, "week_1");
+
+        let full = results.iter().find(|r| r.url == fresh).unwrap();
+        assert_eq!(full.content.as_deref(), Some("body"));
+        assert_eq!(full.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(full.validators("week_2").unwrap().content_run_id, "week_2");
+    }
+
+    #[tokio::test]
+    async fn test_records_redirect_chain_and_metadata() {
+        let base = serve(|request| {
+            if request.starts_with("GET /old ") {
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nServer: test\r\nContent-Length: 4\r\nConnection: close\r\n\r\nbody".to_string()
+            }
+        })
+        .await;
+
+        let downloader = ParallelDownloader::new(local_config()).unwrap();
+        let results = downloader.download_all(vec![format!("{}/old", base)]).await;
+        let result = &results[0];
+
+        assert!(result.success);
+        assert_eq!(result.redirect_chain, vec![format!("{}/old", base)]);
+        assert_eq!(result.final_url.as_deref(), Some(format!("{}/new", base).as_str()));
+        assert_eq!(result.server.as_deref(), Some("test"));
+        assert_eq!(result.content_length, Some(4));
+        assert_eq!(result.remote_ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(result.http_version.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(
+            result.body_sha256.as_deref(),
+            Some("230d8358dc8e8890b4c58deeb62912ee2f20357ae92a5cc861b98e68fe31acb5")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_disallowed_and_oversized_bodies() {
+        let base = serve(|request| {
+            if request.starts_with("GET /video ") {
+                "HTTP/1.1 200 OK\r\nContent-Type: video/mp4\r\nContent-Length: 4\r\nConnection: close\r\n\r\nmp4!".to_string()
+            } else {
+                // No Content-Length: the limit must be enforced while streaming
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}", "x".repeat(4096))
+            }
+        })
+        .await;
+
+        let config = ParallelConfig {
+            max_body_bytes: Some(1024),
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+        let video = format!("{}/video", base);
+        let big = format!("{}/big", base);
+        let results = downloader.download_all(vec![video.clone(), big.clone()]).await;
+
+        let video = results.iter().find(|r| r.url == video).unwrap();
+        assert!(!video.success);
+        assert_eq!(video.error_kind, Some(FailureKind::DisallowedContentType));
+        assert_eq!(video.attempts, 1);
+
+        let big = results.iter().find(|r| r.url == big).unwrap();
+        assert!(!big.success);
+        assert_eq!(big.error_kind, Some(FailureKind::BodyTooLarge));
+    }
+
+    #[test]
+    fn test_content_type_allowed() {
+        let allowed = default_content_types();
+        assert!(content_type_allowed(&allowed, Some("text/html; charset=utf-8")));
+        assert!(content_type_allowed(&allowed, Some("TEXT/PLAIN")));
+        assert!(content_type_allowed(&allowed, None));
+        assert!(!content_type_allowed(&allowed, Some("application/pdf")));
+        assert!(content_type_allowed(&["*/*".to_string()], Some("video/mp4")));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_scheduling() {
+        let base = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\nok".to_string()
+        })
+        .await;
+
+        // One request per 10s to the host: only the first URL starts before shutdown
+        let config = ParallelConfig {
+            politeness: PolitenessConfig {
+                max_per_host: 1,
+                min_delay_ms: 10_000,
+                respect_crawl_delay: false,
+                ..Default::default()
+            },
+            ..local_config()
+        };
+        let shutdown = Shutdown::new();
+        let downloader = ParallelDownloader::new(config)
+            .unwrap()
+            .with_shutdown(shutdown.clone());
+
+        let urls: Vec<String> = (0..3).map(|i| format!("{}/{}", base, i)).collect();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            trigger.trigger();
+        });
+
+        let start = Instant::now();
+        let results: Vec<DownloadResult> = downloader.download_stream(urls).collect().await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_pool_routes_and_ejects_dead_proxy() {
+        // Forward proxies receive the absolute URL in the request line
+        let proxy = serve(|request| {
+            let target = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nproxied {}",
+                target
+            )
+        })
+        .await;
+
+        let config = ParallelConfig {
+            retry: RetryPolicy {
+                max_retries: 2,
+                base_delay_ms: 0,
+                ..Default::default()
+            },
+            proxy: ProxyConfig {
+                proxies: vec!["http://127.0.0.1:1".to_string(), proxy.clone()],
+                eject_after: 1,
+                ..Default::default()
+            },
+            // One request at a time so the ejection is seen by every later pick
+            max_concurrent: 1,
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+        let urls: Vec<String> = (0..4).map(|i| format!("http://site.test/{}", i)).collect();
+        let results = downloader.download_all(urls).await;
+
+        assert!(results.iter().all(|r| r.success));
+        assert!(results.iter().all(|r| r.proxy.as_deref() == Some(proxy.as_str())));
+        let first = results.iter().find(|r| r.url == "http://site.test/0").unwrap();
+        assert_eq!(first.content.as_deref(), Some("proxied http://site.test/0"));
+        // Once the dead proxy is ejected nothing else is sent through it
+        let dead_attempts: usize = results.iter().map(|r| r.failures.len()).sum();
+        assert_eq!(dead_attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_applies_request_profile_for_host() {
+        // Echo the request headers back as the body
+        let base = serve(|request| {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n{}",
+                request.to_lowercase()
+            )
+        })
+        .await;
+
+        let profiles = RequestProfiles::parse(
+            r#"
+            [domains."127.0.0.1"]
+            accept_language = "es-ES"
+            user_agent = "Mozilla/5.0 (perfil)"
+            auth = { type = "bearer", token = "t0k3n" }
+            "#,
+            Path::new("."),
+        )
+        .unwrap();
+
+        let config = ParallelConfig {
+            profiles,
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+        let results = downloader.download_all(vec![format!("{}/es", base)]).await;
+
+        let echoed = results[0].content.as_deref().unwrap();
+        assert!(echoed.contains("accept-language: es-es"));
+        assert!(echoed.contains("user-agent: mozilla/5.0 (perfil)"));
+        assert!(echoed.contains("authorization: bearer t0k3n"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_override_and_dns_failure() {
+        let base = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nmirror".to_string()
+        })
+        .await;
+        let port = Url::parse(&base).unwrap().port().unwrap();
+
+        let mut overrides = HashMap::new();
+        overrides.insert("mirror.test".to_string(), vec!["127.0.0.1".parse().unwrap()]);
+        let config = ParallelConfig {
+            dns: DnsConfig {
+                overrides,
+                ..Default::default()
+            },
+            ..local_config()
+        };
+        let downloader = ParallelDownloader::new(config).unwrap();
+
+        let mirror = format!("http://mirror.test:{}/", port);
+        let missing = "http://does-not-exist.invalid/".to_string();
+        let results = downloader.download_all(vec![mirror.clone(), missing.clone()]).await;
+
+        let mirror = results.iter().find(|r| r.url == mirror).unwrap();
+        assert_eq!(mirror.content.as_deref(), Some("mirror"));
+
+        let missing = results.iter().find(|r| r.url == missing).unwrap();
+        assert_eq!(missing.error_kind, Some(FailureKind::Dns));
+        // Not retried: the lookup would fail (or hit the negative cache) again
+        assert_eq!(missing.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_injected_faults_are_retried_and_reproducible() {
+        let base = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nok".to_string()
+        })
+        .await;
+        // Faults are seeded by URL and the port changes per run, so use enough
+        // URLs that some attempt is always truncated
+        let urls: Vec<String> = (0..40).map(|i| format!("{}/{}", base, i)).collect();
+
+        let run = || async {
+            let config = ParallelConfig {
+                retry: RetryPolicy {
+                    max_retries: 20,
+                    base_delay_ms: 0,
+                    ..Default::default()
+                },
+                breaker: BreakerConfig {
+                    failure_threshold: 1000,
+                    cooldown_secs: 1,
+                },
+                faults: FaultConfig {
+                    seed: 11,
+                    ..FaultConfig::from_str("reset=0.1,503=0.1,truncate=0.3").unwrap()
+                },
+                ..local_config()
+            };
+            let downloader = ParallelDownloader::new(config).unwrap();
+            let mut results = downloader.download_all(urls.clone()).await;
+            results.sort_by(|a, b| a.url.cmp(&b.url));
+            (results, downloader.injected_faults())
+        };
+
+        let (results, injected) = run().await;
+        // Every fault, truncated bodies included, is retried away
+        let failed: Vec<_> = results.iter().filter(|r| !r.success).collect();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert!(results.iter().any(|r| r.success && r.attempts > 1));
+        assert!(injected.get("truncate").is_some_and(|n| *n > 0));
+        let failures: Vec<&AttemptFailure> = results.iter().flat_map(|r| &r.failures).collect();
+        assert!(failures
+            .iter()
+            .all(|f| f.message.starts_with("Injected fault") || f.status_code == Some(503)));
+        assert!(failures.iter().any(|f| {
+            f.kind == FailureKind::ConnectionReset && f.message.contains("bytes of body")
+        }));
+
+        let (again, _) = run().await;
+        let attempts = |results: &[DownloadResult]| -> Vec<u32> {
+            results.iter().map(|r| r.attempts).collect()
+        };
+        assert_eq!(attempts(&results), attempts(&again));
+    }
+
+    #[tokio::test]
+    async fn test_replays_recorded_run() {
+        let dir = std::env::temp_dir().join("jarvix_replay_run_test");
+        std::fs::remove_dir_all(&dir).ok();
+
+        let base = serve(|_| {
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n<p>hola</p>"
+                .to_string()
+        })
+        .await;
+        let urls = vec![format!("{}/page", base), "http://127.0.0.1:1/".to_string()];
+
+        let config = || ParallelConfig {
+            retry: RetryPolicy {
+                max_retries: 0,
+                ..Default::default()
+            },
+            ..local_config()
+        };
+        let recording = ParallelConfig {
+            cassette: Arc::new(Cassette::record(&dir).unwrap()),
+            ..config()
+        };
+        let recorded = ParallelDownloader::new(recording).unwrap().download_all(urls.clone()).await;
+
+        let replaying = ParallelConfig {
+            cassette: Arc::new(Cassette::replay(&dir).unwrap()),
+            ..config()
+        };
+        let replayed = ParallelDownloader::new(replaying).unwrap().download_all(urls).await;
+
+        for original in &recorded {
+            let replay = replayed.iter().find(|r| r.url == original.url).unwrap();
+            assert_eq!(replay.status_code, original.status_code);
+            assert_eq!(replay.content, original.content);
+            assert_eq!(replay.etag, original.etag);
+            assert_eq!(replay.error_kind, original.error_kind);
+        }
+        assert!(replayed.iter().any(|r| r.content.as_deref() == Some("<p>hola</p>")));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
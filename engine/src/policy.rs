@@ -1,10 +1,16 @@
 use anyhow::{Result, anyhow};
+use reqwest::header::LOCATION;
 use reqwest::Client;
 use std::time::Duration;
 use url::Url;
 
+use crate::cassette::Cassette;
+
 const USER_AGENT: &str = "JARVIX-Bot/1.0 (Intelligence Discovery; +https://github.com/Rigohl/JARVIX-MULTISTACK)";
 
+/// Redirects followed when fetching robots.txt
+const MAX_ROBOTS_REDIRECTS: usize = 5;
+
 /// Check if a domain respects robots.txt for our user agent
 pub async fn check_robots_txt(domain: &str) -> Result<bool> {
     let client = Client::builder()
@@ -77,6 +83,72 @@ fn is_allowed_by_robots(robots_txt: &str, user_agent: &str) -> bool {
     true
 }
 
+/// Parse the `Crawl-delay` (in seconds) that applies to our user agent, if any
+pub fn parse_crawl_delay(robots_txt: &str, user_agent: &str) -> Option<f64> {
+    let mut is_relevant_section = false;
+    let mut delay = None;
+
+    for line in robots_txt.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.to_lowercase().starts_with("user-agent:") {
+            let agent = line.split(':').nth(1).unwrap_or("").trim().to_lowercase();
+            is_relevant_section = agent == "*" ||
+                agent.contains("jarvix") ||
+                user_agent.to_lowercase().contains(&agent);
+        } else if line.to_lowercase().starts_with("crawl-delay:") && is_relevant_section {
+            let value = line.split(':').nth(1).unwrap_or("").trim();
+            if let Ok(secs) = value.parse::<f64>() {
+                if secs.is_finite() && secs >= 0.0 {
+                    // Keep the strictest delay if several sections apply
+                    delay = Some(delay.map_or(secs, |d: f64| d.max(secs)));
+                }
+            }
+        }
+    }
+
+    delay
+}
+
+/// Fetch robots.txt for the origin of `url` and return its `Crawl-delay`, if declared
+///
+/// `client` may not follow redirects, so up to `MAX_ROBOTS_REDIRECTS` are
+/// followed here (robots.txt often moves from http to https or to www).
+pub async fn fetch_crawl_delay(
+    cassette: &Cassette,
+    client: &Client,
+    url: &str,
+    user_agent: &str,
+) -> Option<Duration> {
+    let mut robots_url = Url::parse(url).ok()?.join("/robots.txt").ok()?;
+
+    let mut response = cassette.send(client.get(robots_url.clone())).await.ok()?;
+    for _ in 0..MAX_ROBOTS_REDIRECTS {
+        if !response.status().is_redirection() {
+            break;
+        }
+        let location = response.headers().get(LOCATION)?.to_str().ok()?;
+        robots_url = robots_url.join(location).ok()?;
+        response = cassette.send(client.get(robots_url.clone())).await.ok()?;
+    }
+    if !response.status().is_success() {
+        return None;
+    }
+
+    let text = response.text().await.ok()?;
+    parse_crawl_delay(&text, user_agent).map(crawl_delay_duration)
+}
+
+/// Crawl delay in seconds as a `Duration`, saturating on values too large to
+/// represent (the scheduler caps it anyway)
+fn crawl_delay_duration(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+}
+
 /// Validate that a domain is properly formatted
 pub fn validate_domain(domain: &str) -> Result<String> {
     let domain = domain.trim();
@@ -173,3 +245,19 @@ pub async fn is_domain_reachable(domain: &str) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crawl_delay() {
+        let robots = "User-agent: *\nCrawl-delay: 2\n\nUser-agent: otherbot\nCrawl-delay: 60\n";
+        assert_eq!(parse_crawl_delay(robots, USER_AGENT), Some(2.0));
+        assert_eq!(parse_crawl_delay("User-agent: *\nDisallow: /admin\n", USER_AGENT), None);
+
+        let huge = parse_crawl_delay("User-agent: *\nCrawl-delay: 1e20\n", USER_AGENT).unwrap();
+        assert_eq!(crawl_delay_duration(huge), Duration::MAX);
+        assert_eq!(crawl_delay_duration(1.5), Duration::from_millis(1500));
+    }
+}
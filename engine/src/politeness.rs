@@ -0,0 +1,221 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use url::Url;
+
+/// Per-host politeness settings layered on top of the global concurrency limit
+#[derive(Debug, Clone)]
+pub struct PolitenessConfig {
+    /// Maximum concurrent requests to a single host
+    pub max_per_host: usize,
+    /// Minimum delay between two requests to the same host in milliseconds
+    pub min_delay_ms: u64,
+    /// Fetch robots.txt once per host and honor its `Crawl-delay`
+    pub respect_crawl_delay: bool,
+    /// Upper bound for a `Crawl-delay` declared by a site, in seconds
+    pub max_crawl_delay_secs: u64,
+}
+
+impl Default for PolitenessConfig {
+    fn default() -> Self {
+        Self {
+            max_per_host: 8,
+            min_delay_ms: 100,
+            respect_crawl_delay: true,
+            max_crawl_delay_secs: 30,
+        }
+    }
+}
+
+/// Scheduling state for a single host
+struct HostSlot {
+    permits: Arc<Semaphore>,
+    next_request: Mutex<Instant>,
+    crawl_delay: OnceCell<Option<Duration>>,
+}
+
+/// Permit to send one request to a host; released on drop
+pub struct HostPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Per-host scheduler: concurrency caps and request spacing keyed by host
+pub struct HostScheduler {
+    config: PolitenessConfig,
+    hosts: Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+impl HostScheduler {
+    /// Create a new scheduler
+    pub fn new(config: PolitenessConfig) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get or create the scheduling state for a host
+    fn slot(&self, host: &str) -> Arc<HostSlot> {
+        let mut hosts = self.hosts.lock().expect("Host map poisoned");
+        let max_per_host = self.config.max_per_host.max(1);
+
+        Arc::clone(hosts.entry(host.to_string()).or_insert_with(|| {
+            Arc::new(HostSlot {
+                permits: Arc::new(Semaphore::new(max_per_host)),
+                next_request: Mutex::new(Instant::now()),
+                crawl_delay: OnceCell::new(),
+            })
+        }))
+    }
+
+    /// Resolve the `Crawl-delay` of a host once, using `lookup` on first call
+    pub async fn init_crawl_delay<F, Fut>(&self, host: &str, lookup: F)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Option<Duration>>,
+    {
+        if !self.config.respect_crawl_delay {
+            return;
+        }
+
+        let slot = self.slot(host);
+        slot.crawl_delay.get_or_init(lookup).await;
+    }
+
+    /// Delay to enforce between requests to a host
+    pub fn delay_for(&self, host: &str) -> Duration {
+        let slot = self.slot(host);
+        self.effective_delay(&slot)
+    }
+
+    fn effective_delay(&self, slot: &HostSlot) -> Duration {
+        let min_delay = Duration::from_millis(self.config.min_delay_ms);
+        let crawl_delay = slot
+            .crawl_delay
+            .get()
+            .copied()
+            .flatten()
+            .map(|d| d.min(Duration::from_secs(self.config.max_crawl_delay_secs)))
+            .unwrap_or_default();
+
+        min_delay.max(crawl_delay)
+    }
+
+    /// Wait until a request to `host` is allowed and return its permit
+    pub async fn acquire(&self, host: &str) -> HostPermit {
+        let slot = self.slot(host);
+        let permit = Arc::clone(&slot.permits)
+            .acquire_owned()
+            .await
+            .expect("Host semaphore closed");
+
+        let delay = self.effective_delay(&slot);
+        if !delay.is_zero() {
+            // Reserve the next free slot for this host before sleeping so that
+            // concurrent waiters are spaced out instead of waking together
+            let start_at = {
+                let mut next = slot.next_request.lock().expect("Host slot poisoned");
+                let start_at = (*next).max(Instant::now());
+                *next = start_at + delay;
+                start_at
+            };
+            tokio::time::sleep_until(start_at).await;
+        }
+
+        HostPermit { _permit: permit }
+    }
+}
+
+/// Extract the scheduling key (lowercase host) of a URL
+pub fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Reorder URLs round-robin by host so one dominant domain doesn't fill every worker
+pub fn interleave_by_host(urls: Vec<String>) -> Vec<String> {
+    let total = urls.len();
+    let mut order: Vec<String> = Vec::new();
+    let mut queues: HashMap<String, VecDeque<String>> = HashMap::new();
+
+    for url in urls {
+        let host = host_key(&url);
+        if !queues.contains_key(&host) {
+            order.push(host.clone());
+        }
+        queues.entry(host).or_default().push_back(url);
+    }
+
+    let mut result = Vec::with_capacity(total);
+    while result.len() < total {
+        for host in &order {
+            if let Some(url) = queues.get_mut(host).and_then(|q| q.pop_front()) {
+                result.push(url);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave_by_host() {
+        let urls = vec![
+            "https://a.com/1".to_string(),
+            "https://a.com/2".to_string(),
+            "https://a.com/3".to_string(),
+            "https://b.com/1".to_string(),
+        ];
+
+        let result = interleave_by_host(urls);
+        assert_eq!(result[0], "https://a.com/1");
+        assert_eq!(result[1], "https://b.com/1");
+        assert_eq!(result.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_min_delay_between_requests() {
+        let scheduler = HostScheduler::new(PolitenessConfig {
+            max_per_host: 4,
+            min_delay_ms: 50,
+            respect_crawl_delay: false,
+            max_crawl_delay_secs: 30,
+        });
+
+        let start = Instant::now();
+        let _a = scheduler.acquire("example.com").await;
+        let _b = scheduler.acquire("example.com").await;
+        let _c = scheduler.acquire("example.com").await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // Other hosts are not delayed
+        let other = Instant::now();
+        let _d = scheduler.acquire("other.com").await;
+        assert!(other.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay_is_capped() {
+        let scheduler = HostScheduler::new(PolitenessConfig {
+            max_per_host: 1,
+            min_delay_ms: 0,
+            respect_crawl_delay: true,
+            max_crawl_delay_secs: 5,
+        });
+
+        scheduler
+            .init_crawl_delay("slow.com", || async { Some(Duration::from_secs(60)) })
+            .await;
+        assert_eq!(scheduler.delay_for("slow.com"), Duration::from_secs(5));
+        assert_eq!(scheduler.delay_for("fast.com"), Duration::ZERO);
+    }
+}
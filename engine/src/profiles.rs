@@ -0,0 +1,346 @@
+use anyhow::{bail, Context, Result};
+use base64::Engine as _;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_LANGUAGE, AUTHORIZATION, COOKIE, USER_AGENT,
+};
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Request settings for one domain, as written in the profiles file
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RequestProfile {
+    /// Replaces the engine's default user agent
+    pub user_agent: Option<String>,
+    /// Sent as `Accept-Language`, e.g. "es-ES,es;q=0.9"
+    pub accept_language: Option<String>,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Netscape/curl `cookies.txt` file, relative to the profiles file
+    pub cookie_file: Option<PathBuf>,
+    pub auth: Option<Auth>,
+}
+
+/// Credentials for a domain; secrets can be read from environment variables
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    Basic {
+        username: String,
+        password: Option<String>,
+        password_env: Option<String>,
+    },
+    Bearer {
+        token: Option<String>,
+        token_env: Option<String>,
+    },
+}
+
+/// Layout of the profiles TOML file
+///
+/// ```toml
+/// [default]
+/// accept_language = "en-US,en;q=0.9"
+///
+/// [domains."example.es"]          # also matches www.example.es
+/// accept_language = "es-ES,es;q=0.9"
+/// cookie_file = "cookies/example_es.txt"
+/// headers = { "X-Region" = "es" }
+/// auth = { type = "bearer", token_env = "EXAMPLE_ES_TOKEN" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    default: RequestProfile,
+    #[serde(default)]
+    domains: BTreeMap<String, RequestProfile>,
+}
+
+/// One cookie from a cookie jar file
+#[derive(Debug, Clone)]
+struct Cookie {
+    domain: String,
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    /// Unix timestamp; 0 for session cookies
+    expires: i64,
+    name: String,
+    value: String,
+}
+
+impl Cookie {
+    fn matches(&self, url: &Url, now: i64) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let domain_ok = host == self.domain
+            || (self.include_subdomains && host.ends_with(&format!(".{}", self.domain)));
+
+        domain_ok
+            && url.path().starts_with(&self.path)
+            && (!self.secure || url.scheme() == "https")
+            && (self.expires == 0 || self.expires > now)
+    }
+}
+
+/// Profile ready to apply to requests
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedProfile {
+    headers: HeaderMap,
+    cookies: Vec<Cookie>,
+}
+
+impl ResolvedProfile {
+    /// User agent override of this profile, if any
+    pub fn user_agent(&self) -> Option<&str> {
+        self.headers.get(USER_AGENT).and_then(|v| v.to_str().ok())
+    }
+
+    /// Add this profile's headers and matching cookies to a request for `url`
+    pub fn apply(&self, mut request: RequestBuilder, url: &Url) -> RequestBuilder {
+        if !self.headers.is_empty() {
+            request = request.headers(self.headers.clone());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let cookies: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|c| c.matches(url, now))
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        if !cookies.is_empty() {
+            request = request.header(COOKIE, cookies.join("; "));
+        }
+
+        request
+    }
+}
+
+/// Per-domain request profiles
+#[derive(Debug, Clone, Default)]
+pub struct RequestProfiles {
+    default: ResolvedProfile,
+    /// (domain, profile), longest domain first so the most specific match wins
+    domains: Vec<(String, ResolvedProfile)>,
+}
+
+impl RequestProfiles {
+    /// Load profiles from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read request profiles {:?}", path))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&content, base_dir)
+    }
+
+    /// Parse profiles; cookie files are resolved relative to `base_dir`
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let file: ProfilesFile =
+            toml::from_str(content).context("Failed to parse request profiles")?;
+
+        let default = resolve(&file.default, None, base_dir).context("Invalid default profile")?;
+        let mut domains = Vec::new();
+        for (domain, profile) in &file.domains {
+            let resolved = resolve(profile, Some(&file.default), base_dir)
+                .with_context(|| format!("Invalid profile for {}", domain))?;
+            let domain = domain.trim_start_matches("*.").trim_start_matches('.').to_lowercase();
+            domains.push((domain, resolved));
+        }
+        domains.sort_by_key(|(domain, _)| std::cmp::Reverse(domain.len()));
+
+        Ok(Self { default, domains })
+    }
+
+    /// Profile for a host: the longest matching domain, else the default profile
+    pub fn for_host(&self, host: &str) -> &ResolvedProfile {
+        let host = host.to_lowercase();
+        self.domains
+            .iter()
+            .find(|(domain, _)| {
+                host == *domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            })
+            .map_or(&self.default, |(_, profile)| profile)
+    }
+}
+
+/// Merge a profile over the default one and build its headers and cookies
+fn resolve(
+    profile: &RequestProfile,
+    default: Option<&RequestProfile>,
+    base_dir: &Path,
+) -> Result<ResolvedProfile> {
+    let fallback = RequestProfile::default();
+    let default = default.unwrap_or(&fallback);
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in default.headers.iter().chain(&profile.headers) {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name: {}", name))?;
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header {}", name))?;
+        headers.insert(name, value);
+    }
+
+    if let Some(user_agent) = profile.user_agent.as_ref().or(default.user_agent.as_ref()) {
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).context("Invalid user_agent")?,
+        );
+    }
+
+    if let Some(language) = profile.accept_language.as_ref().or(default.accept_language.as_ref()) {
+        headers.insert(
+            ACCEPT_LANGUAGE,
+            HeaderValue::from_str(language).context("Invalid accept_language")?,
+        );
+    }
+
+    if let Some(auth) = profile.auth.as_ref().or(default.auth.as_ref()) {
+        let mut value = HeaderValue::from_str(&authorization(auth)?)
+            .context("Invalid credentials")?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    let cookies = match profile.cookie_file.as_ref().or(default.cookie_file.as_ref()) {
+        Some(file) => {
+            let path = base_dir.join(file);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read cookie file {:?}", path))?;
+            parse_cookie_file(&content)
+        }
+        None => Vec::new(),
+    };
+
+    Ok(ResolvedProfile { headers, cookies })
+}
+
+/// `Authorization` header value for a credential
+fn authorization(auth: &Auth) -> Result<String> {
+    match auth {
+        Auth::Basic {
+            username,
+            password,
+            password_env,
+        } => {
+            let password = secret(password, password_env)?.unwrap_or_default();
+            let encoded = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password));
+            Ok(format!("Basic {}", encoded))
+        }
+        Auth::Bearer { token, token_env } => match secret(token, token_env)? {
+            Some(token) => Ok(format!("Bearer {}", token)),
+            None => bail!("Bearer auth needs `token` or `token_env`"),
+        },
+    }
+}
+
+/// Inline secret, or the value of the named environment variable
+fn secret(inline: &Option<String>, env: &Option<String>) -> Result<Option<String>> {
+    match (inline, env) {
+        (Some(value), _) => Ok(Some(value.clone())),
+        (None, Some(var)) => std::env::var(var)
+            .map(Some)
+            .with_context(|| format!("Environment variable {} is not set", var)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Parse a Netscape/curl `cookies.txt` file, skipping malformed lines
+fn parse_cookie_file(content: &str) -> Vec<Cookie> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // curl marks HttpOnly cookies with this prefix instead of commenting them out
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+                return None;
+            };
+
+            Some(Cookie {
+                domain: domain.trim_start_matches('.').to_lowercase(),
+                include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE"),
+                path: path.to_string(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                expires: expires.parse().unwrap_or(0),
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+        [default]
+        accept_language = "en-US"
+
+        [domains."example.es"]
+        accept_language = "es-ES,es;q=0.9"
+        user_agent = "Mozilla/5.0 (X11; Linux x86_64)"
+        headers = { "X-Region" = "es" }
+        auth = { type = "basic", username = "ana", password = "secreto" }
+
+        [domains."shop.example.es"]
+        auth = { type = "bearer", token = "abc123" }
+    "#;
+
+    #[test]
+    fn test_most_specific_domain_wins() {
+        let profiles = RequestProfiles::parse(PROFILES, Path::new(".")).unwrap();
+
+        let es = profiles.for_host("www.example.es");
+        assert_eq!(es.headers[ACCEPT_LANGUAGE], "es-ES,es;q=0.9");
+        assert_eq!(es.headers["x-region"], "es");
+        assert_eq!(es.user_agent(), Some("Mozilla/5.0 (X11; Linux x86_64)"));
+        assert_eq!(es.headers[AUTHORIZATION], "Basic YW5hOnNlY3JldG8=");
+
+        let shop = profiles.for_host("shop.example.es");
+        assert_eq!(shop.headers[AUTHORIZATION], "Bearer abc123");
+        // Falls back to the default profile, not to the parent domain
+        assert_eq!(shop.headers[ACCEPT_LANGUAGE], "en-US");
+
+        let other = profiles.for_host("notexample.es");
+        assert_eq!(other.headers[ACCEPT_LANGUAGE], "en-US");
+        assert!(other.headers.get(AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn test_cookie_file_matching() {
+        let cookies = parse_cookie_file(
+            "# Netscape HTTP Cookie File\n\
+             .example.es\tTRUE\t/\tFALSE\t0\tlang\tes\n\
+             #HttpOnly_example.es\tFALSE\t/cuenta\tTRUE\t0\tsession\tx1\n\
+             example.es\tFALSE\t/\tFALSE\t1\texpired\tyes\n",
+        );
+        assert_eq!(cookies.len(), 3);
+
+        let now = chrono::Utc::now().timestamp();
+        let matching = |url: &str| -> Vec<&str> {
+            let url = Url::parse(url).unwrap();
+            cookies
+                .iter()
+                .filter(|c| c.matches(&url, now))
+                .map(|c| c.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(matching("https://www.example.es/"), vec!["lang"]);
+        assert_eq!(matching("https://example.es/cuenta/pedidos"), vec!["lang", "session"]);
+        assert_eq!(matching("http://example.es/cuenta"), vec!["lang"]);
+    }
+}
@@ -0,0 +1,337 @@
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::parallel::DownloadEvent;
+use crate::politeness;
+
+/// How run progress is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// Live view on a terminal, periodic log lines otherwise
+    #[default]
+    Auto,
+    Tty,
+    Log,
+    Off,
+}
+
+impl FromStr for ProgressMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ProgressMode::Auto),
+            "tty" => Ok(ProgressMode::Tty),
+            "log" => Ok(ProgressMode::Log),
+            "off" => Ok(ProgressMode::Off),
+            other => bail!("Unknown progress mode '{}' (auto, tty, log or off)", other),
+        }
+    }
+}
+
+/// Counters behind the progress view, updated from download events
+#[derive(Debug)]
+pub struct ProgressTracker {
+    total: usize,
+    started_at: Instant,
+    succeeded: usize,
+    failed: usize,
+    in_flight: HashSet<String>,
+    /// Status code, or failure class when there was no response
+    statuses: BTreeMap<String, usize>,
+    failures_by_host: HashMap<String, usize>,
+}
+
+impl ProgressTracker {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            started_at: Instant::now(),
+            succeeded: 0,
+            failed: 0,
+            in_flight: HashSet::new(),
+            statuses: BTreeMap::new(),
+            failures_by_host: HashMap::new(),
+        }
+    }
+
+    pub fn apply(&mut self, event: &DownloadEvent) {
+        match event {
+            DownloadEvent::Started { url } => {
+                self.in_flight.insert(url.clone());
+            }
+            DownloadEvent::Finished {
+                url,
+                success,
+                status_code,
+                error_kind,
+            } => {
+                self.in_flight.remove(url);
+                let status = match (status_code, error_kind) {
+                    (Some(code), _) => code.to_string(),
+                    (None, Some(kind)) => kind.as_str().to_string(),
+                    (None, None) => "other".to_string(),
+                };
+                *self.statuses.entry(status).or_insert(0) += 1;
+
+                if *success {
+                    self.succeeded += 1;
+                } else {
+                    self.failed += 1;
+                    *self.failures_by_host.entry(politeness::host_key(url)).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.succeeded + self.failed
+    }
+
+    /// Completed URLs per second since the tracker was created
+    pub fn throughput(&self) -> f64 {
+        self.completed() as f64 / self.started_at.elapsed().as_secs_f64().max(0.001)
+    }
+
+    /// Time left at the current throughput (None until something completed)
+    pub fn eta(&self) -> Option<Duration> {
+        let throughput = self.throughput();
+        (self.completed() > 0 && throughput > 0.0).then(|| {
+            let remaining = self.total.saturating_sub(self.completed());
+            Duration::from_secs_f64(remaining as f64 / throughput)
+        })
+    }
+
+    /// Hosts with the most failures, worst first
+    pub fn top_failing_hosts(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut hosts: Vec<(&str, usize)> = self
+            .failures_by_host
+            .iter()
+            .map(|(host, count)| (host.as_str(), *count))
+            .collect();
+        hosts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        hosts.truncate(n);
+        hosts
+    }
+
+    fn percent(&self) -> f64 {
+        self.completed() as f64 * 100.0 / self.total.max(1) as f64
+    }
+
+    fn eta_label(&self) -> String {
+        self.eta().map_or("--".to_string(), format_duration)
+    }
+
+    fn status_summary(&self) -> String {
+        self.statuses
+            .iter()
+            .map(|(status, count)| format!("{}:{}", status, count))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn hosts_summary(&self) -> String {
+        self.top_failing_hosts(5)
+            .iter()
+            .map(|(host, count)| format!("{}:{}", host, count))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// One-line summary for log output
+    pub fn log_line(&self) -> String {
+        let mut line = format!(
+            "Progress: {}/{} ({:.1}%), {} failed, {} in flight, {:.1} URLs/s, ETA {}",
+            self.completed(),
+            self.total,
+            self.percent(),
+            self.failed,
+            self.in_flight.len(),
+            self.throughput(),
+            self.eta_label()
+        );
+        if !self.statuses.is_empty() {
+            line.push_str(&format!("; status {}", self.status_summary()));
+        }
+        if !self.failures_by_host.is_empty() {
+            line.push_str(&format!("; failing hosts {}", self.hosts_summary()));
+        }
+        line
+    }
+
+    /// Lines of the terminal view
+    pub fn render(&self) -> Vec<String> {
+        const WIDTH: usize = 30;
+        let filled = ((self.percent() / 100.0 * WIDTH as f64).round() as usize).min(WIDTH);
+        let bar = format!("{}{}", "#".repeat(filled), "-".repeat(WIDTH - filled));
+
+        vec![
+            format!(
+                "[{}] {:5.1}%  {}/{}",
+                bar,
+                self.percent(),
+                self.completed(),
+                self.total
+            ),
+            format!(
+                "ok {}  failed {}  in flight {}  |  {:.1} URLs/s  ETA {}",
+                self.succeeded,
+                self.failed,
+                self.in_flight.len(),
+                self.throughput(),
+                self.eta_label()
+            ),
+            format!("status         {}", self.status_summary()),
+            format!("failing hosts  {}", self.hosts_summary()),
+        ]
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+/// Running progress view; call `finish` to draw the final state
+pub struct ProgressReporter {
+    done: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl ProgressReporter {
+    pub async fn finish(self) {
+        let _ = self.done.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// Show progress for `total` downloads until `finish` is called or the
+/// event channel closes
+///
+/// Redraws a small live view on stderr when it is a terminal (or in `Tty`
+/// mode); otherwise logs a summary line every `log_interval`.
+pub fn spawn_reporter(
+    total: usize,
+    mut events: mpsc::UnboundedReceiver<DownloadEvent>,
+    mode: ProgressMode,
+    log_interval: Duration,
+) -> ProgressReporter {
+    let (done, mut finished) = oneshot::channel::<()>();
+    let tty = match mode {
+        ProgressMode::Auto => std::io::stderr().is_terminal(),
+        ProgressMode::Tty => true,
+        ProgressMode::Log | ProgressMode::Off => false,
+    };
+
+    let task = tokio::spawn(async move {
+        let mut tracker = ProgressTracker::new(total);
+        if mode == ProgressMode::Off {
+            return;
+        }
+
+        let period = if tty {
+            Duration::from_millis(250)
+        } else {
+            log_interval
+        };
+        let mut ticker = tokio::time::interval(period);
+        ticker.tick().await;
+        let mut drawn = 0;
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => tracker.apply(&event),
+                    None => break,
+                },
+                _ = &mut finished => {
+                    while let Ok(event) = events.try_recv() {
+                        tracker.apply(&event);
+                    }
+                    break;
+                }
+                _ = ticker.tick() => {
+                    if tty {
+                        drawn = redraw(&tracker, drawn);
+                    } else {
+                        info!("{}", tracker.log_line());
+                    }
+                }
+            }
+        }
+
+        if tty {
+            redraw(&tracker, drawn);
+        } else {
+            info!("{}", tracker.log_line());
+        }
+    });
+
+    ProgressReporter { done, task }
+}
+
+/// Replace the previously drawn view; returns the number of lines drawn
+fn redraw(tracker: &ProgressTracker, previous_lines: usize) -> usize {
+    let lines = tracker.render();
+    let mut out = std::io::stderr().lock();
+    if previous_lines > 0 {
+        // Move up over the old view and clear to the end of the screen
+        let _ = write!(out, "\x1b[{}A\x1b[J", previous_lines);
+    }
+    for line in &lines {
+        let _ = writeln!(out, "{}", line);
+    }
+    let _ = out.flush();
+    lines.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::FailureKind;
+
+    fn finished(
+        url: &str,
+        status_code: Option<u16>,
+        error_kind: Option<FailureKind>,
+    ) -> DownloadEvent {
+        DownloadEvent::Finished {
+            url: url.to_string(),
+            success: error_kind.is_none(),
+            status_code,
+            error_kind,
+        }
+    }
+
+    #[test]
+    fn test_tracker_counts_statuses_and_failing_hosts() {
+        let mut tracker = ProgressTracker::new(10);
+        for url in ["https://a.com/1", "https://a.com/2", "https://b.com/1", "https://c.com/1"] {
+            tracker.apply(&DownloadEvent::Started { url: url.to_string() });
+        }
+        tracker.apply(&finished("https://a.com/1", Some(503), Some(FailureKind::ServerError)));
+        tracker.apply(&finished("https://a.com/2", None, Some(FailureKind::Timeout)));
+        tracker.apply(&finished("https://b.com/1", None, Some(FailureKind::Dns)));
+        tracker.apply(&finished("https://c.com/1", Some(200), None));
+
+        assert_eq!(tracker.completed(), 4);
+        assert!(tracker.in_flight.is_empty());
+        assert_eq!(tracker.top_failing_hosts(1), vec![("a.com", 2)]);
+        assert!(tracker.eta().is_some());
+
+        let line = tracker.log_line();
+        assert!(line.contains("4/10 (40.0%), 3 failed"), "{}", line);
+        assert!(line.contains("status 200:1 503:1 dns:1 timeout:1"), "{}", line);
+        assert_eq!(tracker.render().len(), 4);
+    }
+}
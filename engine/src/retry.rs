@@ -0,0 +1,374 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::error::Error as StdError;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::cassette::HttpError;
+use crate::dns::DnsError;
+
+/// Retry behaviour for failed downloads
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum retries per URL (attempts = max_retries + 1)
+    pub max_retries: usize,
+    /// Backoff before the first retry in milliseconds
+    pub base_delay_ms: u64,
+    /// Upper bound for a single backoff in milliseconds
+    pub max_delay_ms: u64,
+    /// Randomize each backoff between 50% and 100% of its value
+    pub jitter: bool,
+    /// Wait as long as the server's `Retry-After` asks (up to `max_retry_after_secs`)
+    pub respect_retry_after: bool,
+    /// Longest `Retry-After` we are willing to wait, in seconds; give up beyond
+    pub max_retry_after_secs: u64,
+    /// Total retries allowed across a whole run (None = unlimited)
+    pub retry_budget: Option<usize>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 10_000,
+            jitter: true,
+            respect_retry_after: true,
+            max_retry_after_secs: 60,
+            retry_budget: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (1-based), ignoring `Retry-After`
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = retry.saturating_sub(1).min(16);
+        let delay_ms = self
+            .base_delay_ms
+            .saturating_mul(1u64 << exp)
+            .min(self.max_delay_ms);
+
+        let delay_ms = if self.jitter && delay_ms > 0 {
+            rand::thread_rng().gen_range(delay_ms / 2..=delay_ms)
+        } else {
+            delay_ms
+        };
+
+        Duration::from_millis(delay_ms)
+    }
+
+    /// Delay before the next attempt after `failure`, or None if the server
+    /// asked us to wait longer than `max_retry_after_secs`
+    pub fn delay_after(&self, retry: u32, failure: &AttemptFailure) -> Option<Duration> {
+        let backoff = self.backoff(retry);
+
+        match failure.retry_after {
+            Some(retry_after) if self.respect_retry_after => {
+                if retry_after > Duration::from_secs(self.max_retry_after_secs) {
+                    return None;
+                }
+                Some(backoff.max(retry_after))
+            }
+            _ => Some(backoff),
+        }
+    }
+}
+
+/// Classification of a failed download attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Host name could not be resolved
+    Dns,
+    Timeout,
+    Connect,
+    ConnectionReset,
+    RateLimited,
+    ServerError,
+    ClientError,
+    Body,
+    /// Host's circuit breaker is open; the request was never sent
+    CircuitOpen,
+    /// Body exceeded the configured size limit
+    BodyTooLarge,
+    /// Response media type is not in the allow list
+    DisallowedContentType,
+    /// Run deadline passed before the URL was fetched
+    SkippedDeadline,
+    Other,
+}
+
+impl FailureKind {
+    /// Whether another attempt could plausibly succeed
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            FailureKind::Timeout
+                | FailureKind::Connect
+                | FailureKind::ConnectionReset
+                | FailureKind::RateLimited
+                | FailureKind::ServerError
+        )
+    }
+
+    /// Classify a non-success HTTP status
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            429 => FailureKind::RateLimited,
+            500..=599 => FailureKind::ServerError,
+            400..=499 => FailureKind::ClientError,
+            _ => FailureKind::Other,
+        }
+    }
+
+    /// Classify a transport error from reqwest
+    pub fn from_reqwest(error: &reqwest::Error) -> Self {
+        if has_source::<DnsError>(error) {
+            return FailureKind::Dns;
+        }
+
+        if error.is_timeout() {
+            return FailureKind::Timeout;
+        }
+
+        if has_connection_reset(error) {
+            return FailureKind::ConnectionReset;
+        }
+
+        if error.is_connect() {
+            FailureKind::Connect
+        } else if error.is_body() || error.is_decode() {
+            FailureKind::Body
+        } else {
+            FailureKind::Other
+        }
+    }
+
+    /// Inverse of `as_str`
+    pub fn from_label(label: &str) -> Option<Self> {
+        const ALL: [FailureKind; 13] = [
+            FailureKind::Dns,
+            FailureKind::Timeout,
+            FailureKind::Connect,
+            FailureKind::ConnectionReset,
+            FailureKind::RateLimited,
+            FailureKind::ServerError,
+            FailureKind::ClientError,
+            FailureKind::Body,
+            FailureKind::CircuitOpen,
+            FailureKind::BodyTooLarge,
+            FailureKind::DisallowedContentType,
+            FailureKind::SkippedDeadline,
+            FailureKind::Other,
+        ];
+        ALL.into_iter().find(|kind| kind.as_str() == label)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureKind::Dns => "dns",
+            FailureKind::Timeout => "timeout",
+            FailureKind::Connect => "connect",
+            FailureKind::ConnectionReset => "connection_reset",
+            FailureKind::RateLimited => "rate_limited",
+            FailureKind::ServerError => "server_error",
+            FailureKind::ClientError => "client_error",
+            FailureKind::Body => "body",
+            FailureKind::CircuitOpen => "circuit_open",
+            FailureKind::BodyTooLarge => "body_too_large",
+            FailureKind::DisallowedContentType => "disallowed_content_type",
+            FailureKind::SkippedDeadline => "skipped_deadline",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+/// Whether an error of type `E` is somewhere in the error chain
+fn has_source<E: StdError + 'static>(error: &reqwest::Error) -> bool {
+    let mut source = error.source();
+    while let Some(err) = source {
+        if err.is::<E>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Walk the error chain looking for a reset/aborted connection
+fn has_connection_reset(error: &reqwest::Error) -> bool {
+    let mut source = error.source();
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            if matches!(
+                io_err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Why a single attempt failed
+#[derive(Debug, Clone)]
+pub struct AttemptFailure {
+    /// Attempt number (1-based)
+    pub attempt: u32,
+    pub kind: FailureKind,
+    pub status_code: Option<u16>,
+    pub message: String,
+    /// Server-requested wait from a `Retry-After` header
+    pub retry_after: Option<Duration>,
+}
+
+impl AttemptFailure {
+    pub fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Self {
+            attempt: 0,
+            kind,
+            status_code: None,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// Failure from a non-success HTTP response
+    pub fn from_status(status: u16, headers: &HeaderMap) -> Self {
+        Self {
+            attempt: 0,
+            kind: FailureKind::from_status(status),
+            status_code: Some(status),
+            message: format!("HTTP {}", status),
+            retry_after: parse_retry_after(headers),
+        }
+    }
+
+    /// Failure from a request sent through a cassette
+    pub fn from_http(error: &HttpError, context: &str) -> Self {
+        match error {
+            HttpError::Network(e) => Self::from_reqwest(e, context),
+            HttpError::Replayed { kind, message } => Self::new(*kind, message.clone()),
+            HttpError::BodyTooLarge { .. } => Self::new(FailureKind::BodyTooLarge, error.to_string()),
+            other => Self::new(FailureKind::Other, format!("{}: {}", context, other)),
+        }
+    }
+
+    /// Failure from a transport error
+    pub fn from_reqwest(error: &reqwest::Error, context: &str) -> Self {
+        Self {
+            attempt: 0,
+            kind: FailureKind::from_reqwest(error),
+            status_code: error.status().map(|s| s.as_u16()),
+            message: format!("{}: {}", context, error),
+            retry_after: None,
+        }
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds or as an HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Shared retry allowance for one download run
+pub struct RetryBudget {
+    remaining: Option<AtomicUsize>,
+}
+
+impl RetryBudget {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            remaining: limit.map(AtomicUsize::new),
+        }
+    }
+
+    /// Take one retry from the budget; false once it is exhausted
+    pub fn try_spend(&self) -> bool {
+        match &self.remaining {
+            None => true,
+            Some(remaining) => remaining
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+                .is_ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_status_classification() {
+        assert!(FailureKind::from_status(503).is_transient());
+        assert!(FailureKind::from_status(429).is_transient());
+        assert!(!FailureKind::from_status(404).is_transient());
+        assert!(!FailureKind::from_status(410).is_transient());
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            base_delay_ms: 100,
+            max_delay_ms: 500,
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff() {
+        let policy = RetryPolicy {
+            jitter: false,
+            max_retry_after_secs: 5,
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        let failure = AttemptFailure::from_status(429, &headers);
+        assert_eq!(failure.retry_after, Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay_after(1, &failure), Some(Duration::from_secs(3)));
+
+        // Asking for more than we are willing to wait means giving up, not retrying early
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        let failure = AttemptFailure::from_status(429, &headers);
+        assert_eq!(failure.retry_after, Some(Duration::from_secs(120)));
+        assert_eq!(policy.delay_after(1, &failure), None);
+
+        let ignoring = RetryPolicy {
+            respect_retry_after: false,
+            ..policy
+        };
+        assert_eq!(ignoring.delay_after(1, &failure), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(Some(2));
+        assert!(budget.try_spend());
+        assert!(budget.try_spend());
+        assert!(!budget.try_spend());
+        assert!(RetryBudget::new(None).try_spend());
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::politeness;
+
+/// Input URL with its scheduling priority (higher is fetched first, default 0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrioritizedUrl {
+    pub url: String,
+    pub priority: i32,
+}
+
+impl From<String> for PrioritizedUrl {
+    fn from(url: String) -> Self {
+        Self { url, priority: 0 }
+    }
+}
+
+/// Parse a URL list: one URL per line, optionally followed by whitespace and
+/// an integer priority; blank lines and `#` comments are skipped
+///
+/// ```text
+/// https://competitor-a.com/pricing  10
+/// https://competitor-b.com/
+/// ```
+pub fn parse_url_list(content: &str) -> Result<Vec<PrioritizedUrl>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| {
+            let mut fields = line.split_whitespace();
+            let url = fields.next().unwrap_or_default().to_string();
+            let priority = match fields.next() {
+                Some(priority) => priority
+                    .parse()
+                    .with_context(|| format!("Invalid priority on line {}: {}", n + 1, line))?,
+                None => 0,
+            };
+            Ok(PrioritizedUrl { url, priority })
+        })
+        .collect()
+}
+
+/// Fetch order: priority tiers from highest to lowest, each interleaved by host
+pub fn order(urls: Vec<PrioritizedUrl>) -> Vec<String> {
+    let mut tiers: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for url in urls {
+        tiers.entry(url.priority).or_default().push(url.url);
+    }
+
+    tiers
+        .into_values()
+        .rev()
+        .flat_map(politeness::interleave_by_host)
+        .collect()
+}
+
+/// Parse a run deadline: a duration from `now` ("90s", "45m", "6h") or an
+/// RFC 3339 timestamp ("2025-06-02T06:00:00Z")
+pub fn parse_deadline(spec: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let spec = spec.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(at.with_timezone(&Utc));
+    }
+
+    let Some((split, _)) = spec.char_indices().last() else {
+        bail!("Empty deadline");
+    };
+    let (amount, unit) = spec.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid deadline '{}': expected e.g. 45m, 6h or a timestamp", spec))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => bail!("Invalid deadline '{}': unit must be s, m or h", spec),
+    };
+
+    amount
+        .checked_mul(unit_secs)
+        .and_then(|secs| chrono::Duration::from_std(Duration::from_secs(secs)).ok())
+        .and_then(|duration| now.checked_add_signed(duration))
+        .context("Deadline too far in the future")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_order_by_priority() {
+        let urls = parse_url_list(
+            "# weekly competitors\n\
+             https://a.com/1\n\
+             https://a.com/2  5\n\
+             https://b.com/1\t5\n\
+             \n\
+             https://c.com/1 -1\n\
+             https://b.com/2\n",
+        )
+        .unwrap();
+        assert_eq!(urls.len(), 5);
+        assert_eq!(urls[1].priority, 5);
+
+        assert_eq!(
+            order(urls),
+            vec![
+                "https://a.com/2",
+                "https://b.com/1",
+                "https://a.com/1",
+                "https://b.com/2",
+                "https://c.com/1",
+            ]
+        );
+        assert!(parse_url_list("https://a.com high").is_err());
+    }
+
+    #[test]
+    fn test_parse_deadline() {
+        let now = Utc::now();
+        assert_eq!(parse_deadline("45m", now).unwrap(), now + chrono::Duration::minutes(45));
+        assert_eq!(parse_deadline("6h", now).unwrap(), now + chrono::Duration::hours(6));
+        assert_eq!(
+            parse_deadline("2025-06-02T06:00:00Z", now).unwrap().to_rfc3339(),
+            "2025-06-02T06:00:00+00:00"
+        );
+        assert!(parse_deadline("soon", now).is_err());
+        assert!(parse_deadline("3d", now).is_err());
+        assert!(parse_deadline("6000000000000000h", now).is_err());
+        assert!(parse_deadline("9000000000000s", now).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Cooperative shutdown flag shared by a run's components
+///
+/// Cloning is cheap; every clone observes the same trigger.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self { sender }
+    }
+
+    /// Ask everything watching this flag to stop
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolve once shutdown has been triggered
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives in `self`, so the channel can't close while we wait
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Trigger on the first SIGINT (Ctrl-C) or SIGTERM
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            warn!("Received {}, shutting down gracefully", signal);
+            shutdown.trigger();
+
+            // A second Ctrl-C means the user doesn't want to wait for the grace period
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("Received second SIGINT, exiting immediately");
+                std::process::exit(130);
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return "SIGINT";
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "SIGINT"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_clones_observe_trigger() {
+        let shutdown = Shutdown::new();
+        let watcher = shutdown.clone();
+        let waiting = tokio::spawn(async move { watcher.wait().await });
+
+        assert!(!shutdown.is_triggered());
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("wait() did not resolve")
+            .unwrap();
+        assert!(shutdown.is_triggered());
+    }
+}
@@ -1,28 +1,182 @@
 use anyhow::{Context, Result};
-use arrow::array::{ArrayRef, StringArray, UInt64Array, BooleanArray};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::array::{
+    Array, ArrayRef, BooleanArray, ListArray, ListBuilder, StringArray, StringBuilder,
+    TimestampMicrosecondArray, UInt64Array,
+};
+use arrow::compute::filter_record_batch;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use arrow::record_batch::RecordBatch;
-use parquet::arrow::ArrowWriter;
-use parquet::file::properties::WriterProperties;
-use parquet::basic::{Compression, GzipLevel};
+use chrono::DateTime;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::{EnabledStatistics, ReaderProperties, WriterProperties};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::serialized_reader::ReadOptionsBuilder as SerializedReadOptionsBuilder;
+use parquet::schema::types::ColumnPath;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::path::Path;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
-use tracing::{info, debug};
+use tracing::{debug, info, warn};
 
 use crate::parallel::DownloadResult;
+use crate::retry::FailureKind;
+
+/// Rows per row group for one-shot saves
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 1000;
+
+/// Compression codec of written Parquet files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Snappy,
+    Lz4,
+    /// Level 0-10
+    Gzip(u32),
+    /// Level 1-22
+    Zstd(i32),
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::Zstd(3)
+    }
+}
+
+impl Codec {
+    fn compression(self) -> Result<Compression> {
+        Ok(match self {
+            Codec::None => Compression::UNCOMPRESSED,
+            Codec::Snappy => Compression::SNAPPY,
+            Codec::Lz4 => Compression::LZ4_RAW,
+            Codec::Gzip(level) => Compression::GZIP(
+                GzipLevel::try_new(level).with_context(|| format!("Invalid gzip level {}", level))?,
+            ),
+            Codec::Zstd(level) => Compression::ZSTD(
+                ZstdLevel::try_new(level).with_context(|| format!("Invalid zstd level {}", level))?,
+            ),
+        })
+    }
+}
+
+impl FromStr for Codec {
+    type Err = anyhow::Error;
+
+    /// "none", "snappy", "lz4", "gzip", "zstd", with an optional level
+    /// for gzip and zstd, e.g. "zstd:9"
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => (name, Some(level)),
+            None => (s, None),
+        };
+
+        let codec = match (name, level) {
+            ("none" | "uncompressed", None) => Codec::None,
+            ("snappy", None) => Codec::Snappy,
+            ("lz4", None) => Codec::Lz4,
+            ("gzip", None) => Codec::Gzip(GzipLevel::default().compression_level()),
+            ("zstd", None) => Codec::default(),
+            ("gzip", Some(level)) => Codec::Gzip(
+                level.parse().with_context(|| format!("Invalid gzip level '{}'", level))?,
+            ),
+            ("zstd", Some(level)) => Codec::Zstd(
+                level.parse().with_context(|| format!("Invalid zstd level '{}'", level))?,
+            ),
+            _ => anyhow::bail!(
+                "Unknown codec '{}' (none, snappy, lz4, gzip[:0-10] or zstd[:1-22])",
+                s
+            ),
+        };
+        codec.compression()?;
+        Ok(codec)
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::None => write!(f, "none"),
+            Codec::Snappy => write!(f, "snappy"),
+            Codec::Lz4 => write!(f, "lz4"),
+            Codec::Gzip(level) => write!(f, "gzip:{}", level),
+            Codec::Zstd(level) => write!(f, "zstd:{}", level),
+        }
+    }
+}
+
+/// How Parquet files are encoded
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub codec: Codec,
+    /// Dictionary-encode repetitive columns such as `error` and `status_code`
+    pub dictionary: bool,
+    /// Write min/max/null-count statistics per page, so readers can skip pages
+    pub statistics: bool,
+    /// False positive rate of the bloom filter on `url` (None = no bloom filter)
+    pub bloom_filter_fpp: Option<f64>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            codec: Codec::default(),
+            dictionary: true,
+            statistics: true,
+            bloom_filter_fpp: Some(0.01),
+        }
+    }
+}
 
 /// Storage manager for Parquet columnar format
+#[derive(Debug, Clone)]
 pub struct ParquetStorage {
-    compression: Compression,
+    config: StorageConfig,
 }
 
 impl ParquetStorage {
     /// Create a new Parquet storage manager
     pub fn new() -> Self {
-        Self {
-            compression: Compression::GZIP(GzipLevel::default()),
+        Self::with_config(StorageConfig::default())
+    }
+
+    pub fn with_config(config: StorageConfig) -> Self {
+        Self { config }
+    }
+
+    /// Writer settings for a table of `R` with row groups of up to `row_group_size`
+    fn writer_properties<R: ParquetRow>(&self, row_group_size: usize) -> Result<WriterProperties> {
+        let statistics = if self.config.statistics {
+            EnabledStatistics::Page
+        } else {
+            EnabledStatistics::None
+        };
+        let mut builder = WriterProperties::builder()
+            .set_compression(self.config.codec.compression()?)
+            .set_max_row_group_size(row_group_size)
+            .set_statistics_enabled(statistics)
+            // Dictionaries of unique values like bodies only cost space
+            .set_dictionary_enabled(false);
+
+        if self.config.dictionary {
+            for column in R::dictionary_columns() {
+                builder = builder.set_column_dictionary_enabled(ColumnPath::from(*column), true);
+            }
         }
+        if let Some(fpp) = self.config.bloom_filter_fpp {
+            for column in R::bloom_filter_columns() {
+                let path = ColumnPath::from(*column);
+                builder = builder
+                    .set_column_bloom_filter_enabled(path.clone(), true)
+                    .set_column_bloom_filter_fpp(path.clone(), fpp)
+                    .set_column_bloom_filter_ndv(path, row_group_size as u64);
+            }
+        }
+
+        Ok(builder.build())
     }
 
     /// Save download results to Parquet file
@@ -34,85 +188,133 @@ impl ParquetStorage {
         let path = output_path.as_ref();
         info!("Saving {} results to Parquet: {:?}", results.len(), path);
 
-        // Ensure parent directory exists
+        let mut writer = self.open_results_writer(path, DEFAULT_ROW_GROUP_SIZE)?;
+        writer.write_all(results)?;
+        writer.close()?;
+
+        let file_size = std::fs::metadata(path)?.len();
+        info!(
+            "Saved {} records to Parquet ({:.2} MB, {} compressed)",
+            results.len(),
+            file_size as f64 / 1_048_576.0,
+            self.config.codec
+        );
+
+        Ok(())
+    }
+
+    /// Open a writer that appends rows to a Parquet file in row groups of
+    /// `row_group_size`
+    pub fn open_writer<R: ParquetRow, P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        row_group_size: usize,
+    ) -> Result<ParquetWriter<R>> {
+        let path = output_path.as_ref();
+        debug!("Streaming rows to Parquet: {:?}", path);
+
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .context("Failed to create parent directory")?;
         }
 
-        // Define schema
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("url", DataType::Utf8, false),
-            Field::new("success", DataType::Boolean, false),
-            Field::new("content", DataType::Utf8, true),
-            Field::new("status_code", DataType::UInt64, true),
-            Field::new("error", DataType::Utf8, true),
-            Field::new("duration_ms", DataType::UInt64, false),
-        ]));
-
-        // Prepare data arrays
-        let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
-        let success: Vec<bool> = results.iter().map(|r| r.success).collect();
-        let contents: Vec<Option<&str>> = results
-            .iter()
-            .map(|r| r.content.as_deref())
-            .collect();
-        let status_codes: Vec<Option<u64>> = results
-            .iter()
-            .map(|r| r.status_code.map(|c| c as u64))
-            .collect();
-        let errors: Vec<Option<&str>> = results
-            .iter()
-            .map(|r| r.error.as_deref())
-            .collect();
-        let durations: Vec<u64> = results.iter().map(|r| r.duration_ms).collect();
-
-        // Create arrays
-        let url_array = Arc::new(StringArray::from(urls)) as ArrayRef;
-        let success_array = Arc::new(BooleanArray::from(success)) as ArrayRef;
-        let content_array = Arc::new(StringArray::from(contents)) as ArrayRef;
-        let status_array = Arc::new(UInt64Array::from(status_codes)) as ArrayRef;
-        let error_array = Arc::new(StringArray::from(errors)) as ArrayRef;
-        let duration_array = Arc::new(UInt64Array::from(durations)) as ArrayRef;
-
-        // Create record batch
-        let batch = RecordBatch::try_new(
-            schema.clone(),
-            vec![
-                url_array,
-                success_array,
-                content_array,
-                status_array,
-                error_array,
-                duration_array,
-            ],
-        )
-        .context("Failed to create record batch")?;
+        let row_group_size = row_group_size.max(1);
+        let schema = R::schema();
+        let file = File::create(path).context("Failed to create output file")?;
+
+        let props = self.writer_properties::<R>(row_group_size)?;
+        let codec = self.config.codec;
+
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))
+            .context("Failed to create Parquet writer")?;
+
+        Ok(ParquetWriter {
+            writer: Some(writer),
+            schema,
+            buffer: Vec::with_capacity(row_group_size),
+            row_group_size,
+            rows_written: 0,
+            path: path.to_path_buf(),
+            codec,
+        })
+    }
+
+    /// Open a writer that appends download results to a Parquet file in row groups
+    pub fn open_results_writer<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        row_group_size: usize,
+    ) -> Result<ResultWriter> {
+        self.open_writer(output_path, row_group_size)
+    }
+
+    /// Open a writer that appends parsed records to a Parquet file in row groups
+    pub fn open_parsed_writer<P: AsRef<Path>>(
+        &self,
+        output_path: P,
+        row_group_size: usize,
+    ) -> Result<ParsedWriter> {
+        self.open_writer(output_path, row_group_size)
+    }
+
+    /// Merge raw result files into one Parquet file, one input at a time
+    ///
+    /// Each input becomes its own row group(s), so memory stays bounded by the
+    /// largest input file rather than the merged total.
+    pub fn merge_results<P: AsRef<Path>>(
+        &self,
+        inputs: &[PathBuf],
+        output_path: P,
+    ) -> Result<usize> {
+        let path = output_path.as_ref();
+        info!("Merging {} Parquet files into {:?}", inputs.len(), path);
 
-        // Write to Parquet
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create parent directory")?;
+        }
+
+        // Size bloom filters for the largest row group: one input's worth
+        let mut largest_input = 1;
+        for input in inputs {
+            largest_input = largest_input.max(self.row_count(input)?);
+        }
+
+        let schema = results_schema();
         let file = File::create(path).context("Failed to create output file")?;
-        
-        let props = WriterProperties::builder()
-            .set_compression(self.compression)
-            .build();
+        let props = self.writer_properties::<DownloadResult>(largest_input)?;
 
         let mut writer = ArrowWriter::try_new(file, schema, Some(props))
             .context("Failed to create Parquet writer")?;
 
-        writer
-            .write(&batch)
-            .context("Failed to write batch")?;
+        let mut rows = 0;
+        for input in inputs {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(
+                File::open(input).with_context(|| format!("Failed to open {:?}", input))?,
+            )
+            .with_context(|| format!("Failed to read Parquet metadata of {:?}", input))?
+            .build()
+            .context("Failed to build Parquet reader")?;
+
+            for batch in reader {
+                let batch = batch.context("Failed to read batch")?;
+                rows += batch.num_rows();
+                writer.write(&batch).context("Failed to write batch")?;
+            }
+            writer.flush().context("Failed to flush row group")?;
+        }
 
         writer.close().context("Failed to close writer")?;
 
         let file_size = std::fs::metadata(path)?.len();
         info!(
-            "Saved {} records to Parquet ({:.2} MB, GZIP compressed)",
-            results.len(),
-            file_size as f64 / 1_048_576.0
+            "Saved {} records to Parquet ({:.2} MB, {} compressed)",
+            rows,
+            file_size as f64 / 1_048_576.0,
+            self.config.codec
         );
 
-        Ok(())
+        Ok(rows)
     }
 
     /// Save parsed data to Parquet (for curated results)
@@ -124,25 +326,267 @@ impl ParquetStorage {
         let path = output_path.as_ref();
         info!("Saving {} parsed records to Parquet: {:?}", data.len(), path);
 
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
+        let mut writer = self.open_parsed_writer(path, DEFAULT_ROW_GROUP_SIZE)?;
+        writer.write_all(data)?;
+        writer.close()?;
+
+        let file_size = std::fs::metadata(path)?.len();
+        debug!("Saved parsed data: {:.2} MB", file_size as f64 / 1_048_576.0);
+
+        Ok(())
+    }
+
+    /// Open a reader over a Parquet table, one record batch at a time
+    pub fn open_reader<R: ParquetRow, P: AsRef<Path>>(
+        &self,
+        input_path: P,
+        options: &ReadOptions,
+    ) -> Result<ParquetReader<R>> {
+        let path = input_path.as_ref();
+        debug!("Reading Parquet: {:?}", path);
+
+        let table_schema = R::schema();
+        if let Some(columns) = &options.columns {
+            for column in columns {
+                if table_schema.field_with_name(column).is_err() {
+                    anyhow::bail!("Unknown column '{}'", column);
+                }
+            }
+        }
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(
+            File::open(path).with_context(|| format!("Failed to open {:?}", path))?,
+        )
+        .with_context(|| format!("Failed to read Parquet metadata of {:?}", path))?;
+
+        // Files written by older versions may lack newer columns; those read as empty
+        let file_schema = builder.schema().clone();
+        let filter_columns = options.filter_columns();
+        for column in &filter_columns {
+            if file_schema.field_with_name(column).is_err() {
+                anyhow::bail!("Cannot filter on '{}': not a column of {:?}", column, path);
+            }
         }
 
-        let schema = Arc::new(Schema::new(vec![
+        let indices: Vec<usize> = file_schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| {
+                filter_columns.contains(&field.name().as_str()) || options.wants(field.name())
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+        let mut builder = builder.with_projection(mask);
+
+        if let Some(url) = &options.url {
+            let row_groups = row_groups_matching(path, "url", url)?;
+            debug!("Bloom filter leaves {} row groups to read for {}", row_groups.len(), url);
+            builder = builder.with_row_groups(row_groups);
+        }
+
+        let batches = builder
+            .build()
+            .context("Failed to build Parquet reader")?;
+
+        Ok(ParquetReader {
+            batches,
+            options: options.clone(),
+            rows: PhantomData,
+        })
+    }
+
+    /// Number of rows in a Parquet file, from its footer
+    pub fn row_count<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let path = path.as_ref();
+        let reader = SerializedFileReader::new(
+            File::open(path).with_context(|| format!("Failed to open {:?}", path))?,
+        )
+        .with_context(|| format!("Failed to read Parquet metadata of {:?}", path))?;
+        Ok(reader.metadata().file_metadata().num_rows().max(0) as usize)
+    }
+
+    /// Read download results back from a raw run file
+    pub fn read_results<P: AsRef<Path>>(
+        &self,
+        input_path: P,
+        options: &ReadOptions,
+    ) -> Result<Vec<DownloadResult>> {
+        let mut results = Vec::new();
+        for batch in self.open_reader(input_path, options)? {
+            results.extend(batch?);
+        }
+        Ok(results)
+    }
+
+    /// Read parsed records back from a curated file
+    pub fn read_parsed_data<P: AsRef<Path>>(
+        &self,
+        input_path: P,
+        options: &ReadOptions,
+    ) -> Result<Vec<ParsedRecord>> {
+        let mut records = Vec::new();
+        for batch in self.open_reader(input_path, options)? {
+            records.extend(batch?);
+        }
+        Ok(records)
+    }
+}
+
+/// Rows that can be stored in a Parquet table
+pub trait ParquetRow: Sized {
+    /// Arrow schema of the table
+    fn schema() -> SchemaRef;
+
+    /// Build a record batch of `rows` with `schema`
+    fn batch(schema: &SchemaRef, rows: &[Self]) -> Result<RecordBatch>;
+
+    /// Rows of a record batch; columns missing from it are left empty
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>>;
+
+    /// Repetitive columns worth dictionary encoding
+    fn dictionary_columns() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Columns looked up by exact value, which get a bloom filter
+    fn bloom_filter_columns() -> &'static [&'static str] {
+        &[]
+    }
+}
+
+impl ParquetRow for DownloadResult {
+    fn schema() -> SchemaRef {
+        results_schema()
+    }
+
+    fn batch(schema: &SchemaRef, rows: &[Self]) -> Result<RecordBatch> {
+        results_batch(schema, rows)
+    }
+
+    fn dictionary_columns() -> &'static [&'static str] {
+        &[
+            "status_code",
+            "error",
+            "error_kind",
+            "attempts",
+            "content_run_id",
+            "content_type",
+            "server",
+            "cache_control",
+            "encoding",
+            "remote_ip",
+            "http_version",
+            "proxy",
+        ]
+    }
+
+    fn bloom_filter_columns() -> &'static [&'static str] {
+        &["url"]
+    }
+
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        let urls = column::<StringArray>(batch, "url")?;
+        let success = column::<BooleanArray>(batch, "success")?;
+        let contents = column::<StringArray>(batch, "content")?;
+        let status_codes = column::<UInt64Array>(batch, "status_code")?;
+        let errors = column::<StringArray>(batch, "error")?;
+        let error_kinds = column::<StringArray>(batch, "error_kind")?;
+        let durations = column::<UInt64Array>(batch, "duration_ms")?;
+        let attempts = column::<UInt64Array>(batch, "attempts")?;
+        let etags = column::<StringArray>(batch, "etag")?;
+        let last_modified = column::<StringArray>(batch, "last_modified")?;
+        let content_run_ids = column::<StringArray>(batch, "content_run_id")?;
+        let final_urls = column::<StringArray>(batch, "final_url")?;
+        let redirect_chains = column::<ListArray>(batch, "redirect_chain")?;
+        let content_types = column::<StringArray>(batch, "content_type")?;
+        let servers = column::<StringArray>(batch, "server")?;
+        let cache_controls = column::<StringArray>(batch, "cache_control")?;
+        let headers = column::<StringArray>(batch, "headers")?;
+        let content_lengths = column::<UInt64Array>(batch, "content_length")?;
+        let body_hashes = column::<StringArray>(batch, "body_sha256")?;
+        let encodings = column::<StringArray>(batch, "encoding")?;
+        let fetched_at = column::<TimestampMicrosecondArray>(batch, "fetched_at")?;
+        let remote_ips = column::<StringArray>(batch, "remote_ip")?;
+        let http_versions = column::<StringArray>(batch, "http_version")?;
+        let proxies = column::<StringArray>(batch, "proxy")?;
+        let content_blobs = column::<StringArray>(batch, "content_blob")?;
+
+        (0..batch.num_rows())
+            .map(|row| {
+                let redirect_chain = match value(redirect_chains, row, ListArray::value) {
+                    Some(hops) => hops
+                        .as_any()
+                        .downcast_ref::<StringArray>()
+                        .context("Column 'redirect_chain' is not a list of strings")?
+                        .iter()
+                        .flatten()
+                        .map(String::from)
+                        .collect(),
+                    None => Vec::new(),
+                };
+                let headers = match text(headers, row) {
+                    Some(json) => {
+                        serde_json::from_str(&json).context("Invalid JSON in column 'headers'")?
+                    }
+                    None => BTreeMap::new(),
+                };
+
+                Ok(DownloadResult {
+                    url: text(urls, row).unwrap_or_default(),
+                    success: value(success, row, BooleanArray::value).unwrap_or_default(),
+                    content: text(contents, row),
+                    status_code: number(status_codes, row).map(|c| c as u16),
+                    error: text(errors, row),
+                    error_kind: text(error_kinds, row)
+                        .and_then(|kind| FailureKind::from_label(&kind)),
+                    duration_ms: number(durations, row).unwrap_or_default(),
+                    attempts: number(attempts, row).unwrap_or_default() as u32,
+                    failures: Vec::new(),
+                    etag: text(etags, row),
+                    last_modified: text(last_modified, row),
+                    content_run_id: text(content_run_ids, row),
+                    final_url: text(final_urls, row),
+                    redirect_chain,
+                    content_type: text(content_types, row),
+                    server: text(servers, row),
+                    cache_control: text(cache_controls, row),
+                    headers,
+                    content_length: number(content_lengths, row),
+                    body_sha256: text(body_hashes, row),
+                    encoding: text(encodings, row),
+                    fetched_at: value(fetched_at, row, TimestampMicrosecondArray::value)
+                        .and_then(DateTime::from_timestamp_micros),
+                    remote_ip: text(remote_ips, row),
+                    http_version: text(http_versions, row),
+                    proxy: text(proxies, row),
+                    content_blob: text(content_blobs, row),
+                })
+            })
+            .collect()
+    }
+}
+
+impl ParquetRow for ParsedRecord {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
             Field::new("canonical_id", DataType::Utf8, false),
             Field::new("title", DataType::Utf8, true),
             Field::new("text_length", DataType::UInt64, false),
             Field::new("has_buy_keywords", DataType::Boolean, false),
             Field::new("quality_score", DataType::UInt64, false),
-        ]));
+        ]))
+    }
 
-        let ids: Vec<&str> = data.iter().map(|r| r.canonical_id.as_str()).collect();
-        let titles: Vec<Option<&str>> = data.iter().map(|r| r.title.as_deref()).collect();
-        let lengths: Vec<u64> = data.iter().map(|r| r.text_length as u64).collect();
-        let buy_keywords: Vec<bool> = data.iter().map(|r| r.has_buy_keywords).collect();
-        let quality: Vec<u64> = data.iter().map(|r| r.quality_score as u64).collect();
+    fn batch(schema: &SchemaRef, rows: &[Self]) -> Result<RecordBatch> {
+        let ids: Vec<&str> = rows.iter().map(|r| r.canonical_id.as_str()).collect();
+        let titles: Vec<Option<&str>> = rows.iter().map(|r| r.title.as_deref()).collect();
+        let lengths: Vec<u64> = rows.iter().map(|r| r.text_length as u64).collect();
+        let buy_keywords: Vec<bool> = rows.iter().map(|r| r.has_buy_keywords).collect();
+        let quality: Vec<u64> = rows.iter().map(|r| r.quality_score as u64).collect();
 
-        let batch = RecordBatch::try_new(
+        RecordBatch::try_new(
             schema.clone(),
             vec![
                 Arc::new(StringArray::from(ids)) as ArrayRef,
@@ -151,22 +595,500 @@ impl ParquetStorage {
                 Arc::new(BooleanArray::from(buy_keywords)) as ArrayRef,
                 Arc::new(UInt64Array::from(quality)) as ArrayRef,
             ],
-        )?;
+        )
+        .context("Failed to create record batch")
+    }
 
-        let file = File::create(path)?;
-        let props = WriterProperties::builder()
-            .set_compression(self.compression)
-            .build();
+    fn bloom_filter_columns() -> &'static [&'static str] {
+        &["canonical_id"]
+    }
 
-        let mut writer = ArrowWriter::try_new(file, schema, Some(props))?;
-        writer.write(&batch)?;
-        writer.close()?;
+    fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>> {
+        let ids = column::<StringArray>(batch, "canonical_id")?;
+        let titles = column::<StringArray>(batch, "title")?;
+        let lengths = column::<UInt64Array>(batch, "text_length")?;
+        let buy_keywords = column::<BooleanArray>(batch, "has_buy_keywords")?;
+        let quality = column::<UInt64Array>(batch, "quality_score")?;
 
-        let file_size = std::fs::metadata(path)?.len();
-        debug!("Saved parsed data: {:.2} MB", file_size as f64 / 1_048_576.0);
+        Ok((0..batch.num_rows())
+            .map(|row| ParsedRecord {
+                canonical_id: text(ids, row).unwrap_or_default(),
+                title: text(titles, row),
+                text_length: number(lengths, row).unwrap_or_default() as usize,
+                has_buy_keywords: value(buy_keywords, row, BooleanArray::value)
+                    .unwrap_or_default(),
+                quality_score: number(quality, row).unwrap_or_default() as u32,
+            })
+            .collect())
+    }
+}
+
+/// Row groups of a Parquet file that may hold `value` in column `name`,
+/// judged by the column's bloom filters (all row groups when there are none)
+fn row_groups_matching(path: &Path, name: &str, value: &str) -> Result<Vec<usize>> {
+    let options = SerializedReadOptionsBuilder::new()
+        .with_reader_properties(ReaderProperties::builder().set_read_bloom_filter(true).build())
+        .build();
+    let reader = SerializedFileReader::new_with_options(
+        File::open(path).with_context(|| format!("Failed to open {:?}", path))?,
+        options,
+    )
+    .with_context(|| format!("Failed to read Parquet metadata of {:?}", path))?;
+
+    let metadata = reader.metadata();
+    let Some(column) = metadata
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .position(|c| c.path().string() == name)
+    else {
+        return Ok((0..metadata.num_row_groups()).collect());
+    };
+
+    let mut row_groups = Vec::new();
+    for i in 0..metadata.num_row_groups() {
+        let row_group = reader.get_row_group(i).context("Failed to read row group")?;
+        let may_contain = row_group
+            .get_column_bloom_filter(column)
+            .is_none_or(|bloom| bloom.check(&value));
+        if may_contain {
+            row_groups.push(i);
+        }
+    }
+    Ok(row_groups)
+}
+
+/// Column `name` of `batch` as array type `A`, if the batch has it
+fn column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<Option<&'a A>> {
+    batch
+        .column_by_name(name)
+        .map(|array| {
+            array.as_any().downcast_ref::<A>().with_context(|| {
+                format!("Column '{}' has unexpected type {}", name, array.data_type())
+            })
+        })
+        .transpose()
+}
+
+/// Non-null value at `row` of an optional column
+fn value<A: Array, T>(array: Option<&A>, row: usize, get: impl Fn(&A, usize) -> T) -> Option<T> {
+    array.filter(|a| a.is_valid(row)).map(|a| get(a, row))
+}
+
+fn text(array: Option<&StringArray>, row: usize) -> Option<String> {
+    value(array, row, |a, i| a.value(i).to_string())
+}
+
+fn number(array: Option<&UInt64Array>, row: usize) -> Option<u64> {
+    value(array, row, UInt64Array::value)
+}
+
+/// Which columns and rows to read back from a Parquet table
+///
+/// Filters work on columns of the raw results table; filtering on a column
+/// the file does not have is an error.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Columns to load, e.g. everything but `content` (None = all); fields
+    /// of columns not loaded are left empty
+    pub columns: Option<Vec<String>>,
+    /// Keep rows with one of these status codes (empty = any)
+    pub status_codes: Vec<u16>,
+    /// Keep only successful (true) or only failed (false) downloads
+    pub success: Option<bool>,
+    /// Keep rows whose URL starts with this prefix
+    pub url_prefix: Option<String>,
+    /// Keep rows with exactly this URL; row groups whose bloom filter rules
+    /// it out are not read at all
+    pub url: Option<String>,
+}
+
+impl ReadOptions {
+    /// Whether column `name` was asked for
+    fn wants(&self, name: &str) -> bool {
+        self.columns
+            .as_ref()
+            .is_none_or(|columns| columns.iter().any(|c| c == name))
+    }
+
+    /// Columns the filters need
+    fn filter_columns(&self) -> Vec<&'static str> {
+        let mut columns = Vec::new();
+        if !self.status_codes.is_empty() {
+            columns.push("status_code");
+        }
+        if self.success.is_some() {
+            columns.push("success");
+        }
+        if self.url_prefix.is_some() || self.url.is_some() {
+            columns.push("url");
+        }
+        columns
+    }
+
+    /// Drop rows that fail the filters, then columns only loaded for them
+    fn apply(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let mut keep = vec![true; batch.num_rows()];
+
+        if !self.status_codes.is_empty() {
+            let codes = column::<UInt64Array>(&batch, "status_code")?;
+            for (row, keep) in keep.iter_mut().enumerate() {
+                *keep &= number(codes, row)
+                    .is_some_and(|code| self.status_codes.iter().any(|c| u64::from(*c) == code));
+            }
+        }
+        if let Some(success) = self.success {
+            let flags = column::<BooleanArray>(&batch, "success")?;
+            for (row, keep) in keep.iter_mut().enumerate() {
+                *keep &= value(flags, row, BooleanArray::value) == Some(success);
+            }
+        }
+        if let Some(prefix) = &self.url_prefix {
+            let urls = column::<StringArray>(&batch, "url")?;
+            for (row, keep) in keep.iter_mut().enumerate() {
+                *keep &= value(urls, row, |a, i| a.value(i).starts_with(prefix.as_str()))
+                    .unwrap_or(false);
+            }
+        }
+
+        if let Some(wanted) = &self.url {
+            let urls = column::<StringArray>(&batch, "url")?;
+            for (row, keep) in keep.iter_mut().enumerate() {
+                *keep &= value(urls, row, |a, i| a.value(i) == wanted.as_str()).unwrap_or(false);
+            }
+        }
+
+        let batch = if keep.iter().all(|k| *k) {
+            batch
+        } else {
+            filter_record_batch(&batch, &BooleanArray::from(keep))
+                .context("Failed to filter record batch")?
+        };
+
+        if self.columns.is_none() {
+            return Ok(batch);
+        }
+        let indices: Vec<usize> = batch
+            .schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| self.wants(field.name()))
+            .map(|(i, _)| i)
+            .collect();
+        batch.project(&indices).context("Failed to project record batch")
+    }
+}
+
+/// Reader over a Parquet table, yielding the rows of one record batch at a time
+pub struct ParquetReader<R: ParquetRow> {
+    batches: ParquetRecordBatchReader,
+    options: ReadOptions,
+    rows: PhantomData<R>,
+}
+
+impl<R: ParquetRow> Iterator for ParquetReader<R> {
+    type Item = Result<Vec<R>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let batch = match self.batches.next()? {
+                Ok(batch) => batch,
+                Err(e) => return Some(Err(e).context("Failed to read batch")),
+            };
+            match self.options.apply(batch) {
+                Ok(batch) if batch.num_rows() == 0 => continue,
+                Ok(batch) => return Some(R::from_batch(&batch)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Schema of raw download results
+fn results_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("url", DataType::Utf8, false),
+        Field::new("success", DataType::Boolean, false),
+        Field::new("content", DataType::Utf8, true),
+        Field::new("status_code", DataType::UInt64, true),
+        Field::new("error", DataType::Utf8, true),
+        Field::new("error_kind", DataType::Utf8, true),
+        Field::new("duration_ms", DataType::UInt64, false),
+        Field::new("attempts", DataType::UInt64, false),
+        Field::new("etag", DataType::Utf8, true),
+        Field::new("last_modified", DataType::Utf8, true),
+        Field::new("content_run_id", DataType::Utf8, true),
+        Field::new("final_url", DataType::Utf8, true),
+        Field::new(
+            "redirect_chain",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            false,
+        ),
+        Field::new("content_type", DataType::Utf8, true),
+        Field::new("server", DataType::Utf8, true),
+        Field::new("cache_control", DataType::Utf8, true),
+        Field::new("headers", DataType::Utf8, true),
+        Field::new("content_length", DataType::UInt64, true),
+        Field::new("body_sha256", DataType::Utf8, true),
+        Field::new("encoding", DataType::Utf8, true),
+        Field::new(
+            "fetched_at",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            true,
+        ),
+        Field::new("remote_ip", DataType::Utf8, true),
+        Field::new("http_version", DataType::Utf8, true),
+        Field::new("proxy", DataType::Utf8, true),
+        Field::new("content_blob", DataType::Utf8, true),
+    ]))
+}
+
+/// Build a record batch of download results
+fn results_batch(schema: &SchemaRef, results: &[DownloadResult]) -> Result<RecordBatch> {
+    // Prepare data arrays
+    let urls: Vec<&str> = results.iter().map(|r| r.url.as_str()).collect();
+    let success: Vec<bool> = results.iter().map(|r| r.success).collect();
+    let contents: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.content.as_deref())
+        .collect();
+    let status_codes: Vec<Option<u64>> = results
+        .iter()
+        .map(|r| r.status_code.map(|c| c as u64))
+        .collect();
+    let errors: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.error.as_deref())
+        .collect();
+    let error_kinds: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.error_kind.map(|k| k.as_str()))
+        .collect();
+    let durations: Vec<u64> = results.iter().map(|r| r.duration_ms).collect();
+    let attempts: Vec<u64> = results.iter().map(|r| r.attempts as u64).collect();
+    let etags: Vec<Option<&str>> = results.iter().map(|r| r.etag.as_deref()).collect();
+    let last_modified: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.last_modified.as_deref())
+        .collect();
+    let content_run_ids: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.content_run_id.as_deref())
+        .collect();
+    let final_urls: Vec<Option<&str>> = results.iter().map(|r| r.final_url.as_deref()).collect();
+    let content_types: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.content_type.as_deref())
+        .collect();
+    let servers: Vec<Option<&str>> = results.iter().map(|r| r.server.as_deref()).collect();
+    let cache_controls: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.cache_control.as_deref())
+        .collect();
+    let headers: Vec<Option<String>> = results
+        .iter()
+        .map(|r| {
+            if r.headers.is_empty() {
+                None
+            } else {
+                serde_json::to_string(&r.headers).ok()
+            }
+        })
+        .collect();
+    let content_lengths: Vec<Option<u64>> = results.iter().map(|r| r.content_length).collect();
+    let body_hashes: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.body_sha256.as_deref())
+        .collect();
+    let encodings: Vec<Option<&str>> = results.iter().map(|r| r.encoding.as_deref()).collect();
+    let fetched_at: Vec<Option<i64>> = results
+        .iter()
+        .map(|r| r.fetched_at.map(|t| t.timestamp_micros()))
+        .collect();
+    let remote_ips: Vec<Option<&str>> = results.iter().map(|r| r.remote_ip.as_deref()).collect();
+    let http_versions: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.http_version.as_deref())
+        .collect();
+    let proxies: Vec<Option<&str>> = results.iter().map(|r| r.proxy.as_deref()).collect();
+    let content_blobs: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.content_blob.as_deref())
+        .collect();
+
+    let mut redirect_chains = ListBuilder::new(StringBuilder::new());
+    for result in results {
+        for hop in &result.redirect_chain {
+            redirect_chains.values().append_value(hop);
+        }
+        redirect_chains.append(true);
+    }
+
+    RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(StringArray::from(urls)) as ArrayRef,
+            Arc::new(BooleanArray::from(success)) as ArrayRef,
+            Arc::new(StringArray::from(contents)) as ArrayRef,
+            Arc::new(UInt64Array::from(status_codes)) as ArrayRef,
+            Arc::new(StringArray::from(errors)) as ArrayRef,
+            Arc::new(StringArray::from(error_kinds)) as ArrayRef,
+            Arc::new(UInt64Array::from(durations)) as ArrayRef,
+            Arc::new(UInt64Array::from(attempts)) as ArrayRef,
+            Arc::new(StringArray::from(etags)) as ArrayRef,
+            Arc::new(StringArray::from(last_modified)) as ArrayRef,
+            Arc::new(StringArray::from(content_run_ids)) as ArrayRef,
+            Arc::new(StringArray::from(final_urls)) as ArrayRef,
+            Arc::new(redirect_chains.finish()) as ArrayRef,
+            Arc::new(StringArray::from(content_types)) as ArrayRef,
+            Arc::new(StringArray::from(servers)) as ArrayRef,
+            Arc::new(StringArray::from(cache_controls)) as ArrayRef,
+            Arc::new(StringArray::from(headers)) as ArrayRef,
+            Arc::new(UInt64Array::from(content_lengths)) as ArrayRef,
+            Arc::new(StringArray::from(body_hashes)) as ArrayRef,
+            Arc::new(StringArray::from(encodings)) as ArrayRef,
+            Arc::new(TimestampMicrosecondArray::from(fetched_at).with_timezone("UTC")) as ArrayRef,
+            Arc::new(StringArray::from(remote_ips)) as ArrayRef,
+            Arc::new(StringArray::from(http_versions)) as ArrayRef,
+            Arc::new(StringArray::from(proxies)) as ArrayRef,
+            Arc::new(StringArray::from(content_blobs)) as ArrayRef,
+        ],
+    )
+    .context("Failed to create record batch")
+}
+
+/// Incremental Parquet writer
+///
+/// Rows are buffered until `row_group_size` is reached and then written out
+/// as one row group, so only one row group is held in memory at a time.
+/// A writer dropped without `close` (e.g. on an error path) still writes
+/// its buffered rows and the file footer, leaving a readable file.
+pub struct ParquetWriter<R: ParquetRow> {
+    /// None once the file has been finalized
+    writer: Option<ArrowWriter<File>>,
+    schema: SchemaRef,
+    buffer: Vec<R>,
+    row_group_size: usize,
+    rows_written: usize,
+    path: PathBuf,
+    codec: Codec,
+}
+
+/// Writer for raw download results
+pub type ResultWriter = ParquetWriter<DownloadResult>;
+
+/// Writer for parsed records
+pub type ParsedWriter = ParquetWriter<ParsedRecord>;
+
+impl<R: ParquetRow> ParquetWriter<R> {
+    /// Add one row, flushing a row group when the buffer is full
+    pub fn write(&mut self, row: R) -> Result<()> {
+        self.buffer.push(row);
+        if self.buffer.len() >= self.row_group_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Add rows as they arrive, flushing full row groups along the way
+    pub fn write_batch<I: IntoIterator<Item = R>>(&mut self, rows: I) -> Result<()> {
+        for row in rows {
+            self.write(row)?;
+        }
+        Ok(())
+    }
+
+    /// Write borrowed rows without buffering them, in row groups of up to
+    /// `row_group_size` (buffered rows are flushed first)
+    pub fn write_all(&mut self, rows: &[R]) -> Result<()> {
+        self.flush()?;
+        for chunk in rows.chunks(self.row_group_size) {
+            self.write_row_group(chunk)?;
+            self.rows_written += chunk.len();
+        }
+        Ok(())
+    }
+
+    /// Write buffered rows as a row group
+    pub fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let rows = std::mem::take(&mut self.buffer);
+        self.write_row_group(&rows)?;
+        self.rows_written += rows.len();
+        debug!("Flushed row group of {} rows to {:?}", rows.len(), self.path);
+
+        // Reuse the allocation for the next row group
+        self.buffer = rows;
+        self.buffer.clear();
 
         Ok(())
     }
+
+    fn write_row_group(&mut self, rows: &[R]) -> Result<()> {
+        let writer = self.writer.as_mut().context("Parquet writer is closed")?;
+        let batch = R::batch(&self.schema, rows)?;
+        writer.write(&batch).context("Failed to write batch")?;
+        writer.flush().context("Failed to flush row group")?;
+        Ok(())
+    }
+
+    /// Number of rows written so far, including buffered ones
+    pub fn len(&self) -> usize {
+        self.rows_written + self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Flush remaining rows and finalize the file; returns rows written
+    pub fn close(mut self) -> Result<usize> {
+        self.finish()?;
+
+        let file_size = std::fs::metadata(&self.path)?.len();
+        debug!(
+            "Saved {} records to Parquet ({:.2} MB, {} compressed)",
+            self.rows_written,
+            file_size as f64 / 1_048_576.0,
+            self.codec
+        );
+
+        Ok(self.rows_written)
+    }
+
+    /// Write buffered rows and the footer; the file is finalized even when
+    /// the last row group fails
+    fn finish(&mut self) -> Result<()> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+
+        let flushed = self.flush();
+        let writer = self.writer.take().context("Parquet writer is closed")?;
+        let closed = writer.close().context("Failed to close writer");
+        flushed.and(closed.map(|_| ()))
+    }
+}
+
+impl<R: ParquetRow> Drop for ParquetWriter<R> {
+    fn drop(&mut self) {
+        if self.writer.is_none() {
+            return;
+        }
+
+        match self.finish() {
+            Ok(()) => debug!(
+                "Finalized {:?} with {} rows after the writer was dropped",
+                self.path, self.rows_written
+            ),
+            Err(e) => warn!("Failed to finalize Parquet file {:?}: {:#}", self.path, e),
+        }
+    }
 }
 
 impl Default for ParquetStorage {
@@ -202,6 +1124,8 @@ mod tests {
                 status_code: Some(200),
                 error: None,
                 duration_ms: 100,
+                attempts: 1,
+                ..Default::default()
             },
         ];
 
@@ -213,4 +1137,182 @@ mod tests {
         
         std::fs::remove_file(output_path).ok();
     }
+
+    #[test]
+    fn test_results_writer_flushes_row_groups() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let storage = ParquetStorage::new();
+        let output_path = std::env::temp_dir().join("test_results_writer.parquet");
+
+        let mut writer = storage.open_results_writer(&output_path, 2).unwrap();
+        for i in 0..5 {
+            writer
+                .write(DownloadResult {
+                    url: format!("https://example.com/{}", i),
+                    success: true,
+                    content: Some("<html>test</html>".to_string()),
+                    status_code: Some(200),
+                    attempts: 1,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        assert_eq!(writer.close().unwrap(), 5);
+
+        let reader = SerializedFileReader::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 3);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 5);
+
+        std::fs::remove_file(output_path).ok();
+    }
+
+    #[test]
+    fn test_read_results_with_projection_and_filters() {
+        let storage = ParquetStorage::new();
+        let output_path = std::env::temp_dir().join("test_read_results.parquet");
+
+        let results: Vec<DownloadResult> = (0..6)
+            .map(|i| {
+                let status = [200, 404, 503][i % 3];
+                DownloadResult {
+                    url: format!("https://{}.example.com/{}", if i < 4 { "shop" } else { "blog" }, i),
+                    success: status == 200,
+                    content: (status == 200).then(|| "<html>ok</html>".to_string()),
+                    status_code: Some(status),
+                    error_kind: (status != 200).then(|| FailureKind::from_status(status)),
+                    attempts: 1,
+                    redirect_chain: vec!["http://example.com/".to_string()],
+                    headers: BTreeMap::from([("server".to_string(), "nginx".to_string())]),
+                    fetched_at: DateTime::from_timestamp(1_750_000_000, 0),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let mut writer = storage.open_results_writer(&output_path, 4).unwrap();
+        writer.write_batch(results.clone()).unwrap();
+        writer.close().unwrap();
+
+        let all = storage.read_results(&output_path, &ReadOptions::default()).unwrap();
+        assert_eq!(all.len(), 6);
+        assert_eq!(all[2].error_kind, Some(FailureKind::ServerError));
+        assert_eq!(all[0].redirect_chain, results[0].redirect_chain);
+        assert_eq!(all[0].headers, results[0].headers);
+        assert_eq!(all[0].fetched_at, results[0].fetched_at);
+
+        let options = ReadOptions {
+            columns: Some(vec!["url".to_string(), "status_code".to_string()]),
+            success: Some(false),
+            url_prefix: Some("https://shop.".to_string()),
+            ..Default::default()
+        };
+        let failed = storage.read_results(&output_path, &options).unwrap();
+        let urls: Vec<&str> = failed.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://shop.example.com/1", "https://shop.example.com/2"]);
+        assert!(failed.iter().all(|r| r.content.is_none() && r.error_kind.is_none()));
+
+        let options = ReadOptions {
+            status_codes: vec![503],
+            ..Default::default()
+        };
+        assert_eq!(storage.read_results(&output_path, &options).unwrap().len(), 2);
+
+        let options = ReadOptions {
+            columns: Some(vec!["body".to_string()]),
+            ..Default::default()
+        };
+        assert!(storage.read_results(&output_path, &options).is_err());
+
+        std::fs::remove_file(output_path).ok();
+    }
+
+    #[test]
+    fn test_codecs_encodings_and_url_bloom_filter() {
+        use parquet::basic::Encoding;
+
+        assert_eq!("zstd:9".parse::<Codec>().unwrap(), Codec::Zstd(9));
+        assert_eq!("snappy".parse::<Codec>().unwrap().to_string(), "snappy");
+        assert!("zstd:40".parse::<Codec>().is_err());
+        assert!("lzo".parse::<Codec>().is_err());
+
+        let storage = ParquetStorage::with_config(StorageConfig {
+            codec: Codec::Zstd(5),
+            ..Default::default()
+        });
+        let output_path = std::env::temp_dir().join("test_bloom_filter.parquet");
+        let mut writer = storage.open_results_writer(&output_path, 10).unwrap();
+        for i in 0..50 {
+            writer
+                .write(DownloadResult {
+                    url: format!("https://example.com/{}", i),
+                    error: Some("HTTP 404".to_string()),
+                    status_code: Some(404),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        writer.close().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&output_path).unwrap()).unwrap();
+        let row_group = reader.metadata().row_group(0);
+        let column = |name: &str| {
+            row_group
+                .columns()
+                .iter()
+                .find(|c| c.column_path().string() == name)
+                .unwrap()
+        };
+        assert!(matches!(column("url").compression(), Compression::ZSTD(_)));
+        assert!(column("url").bloom_filter_offset().is_some());
+        assert!(column("url").statistics().is_some());
+        assert!(column("error").encodings().contains(&Encoding::RLE_DICTIONARY));
+        assert!(!column("content").encodings().contains(&Encoding::RLE_DICTIONARY));
+
+        let options = ReadOptions {
+            url: Some("https://example.com/42".to_string()),
+            ..Default::default()
+        };
+        let path = output_path.as_path();
+        let row_groups = row_groups_matching(path, "url", "https://example.com/42").unwrap();
+        assert!(row_groups.contains(&4) && row_groups.len() < 5, "{:?}", row_groups);
+        let found = storage.read_results(path, &options).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].status_code, Some(404));
+
+        std::fs::remove_file(output_path).ok();
+    }
+
+    #[test]
+    fn test_dropped_parsed_writer_leaves_readable_file() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let storage = ParquetStorage::new();
+        let output_path = std::env::temp_dir().join("test_parsed_writer.parquet");
+        let record = |i: usize| ParsedRecord {
+            canonical_id: format!("id-{}", i),
+            title: Some("Pricing".to_string()),
+            text_length: 100 * i,
+            has_buy_keywords: i < 2,
+            quality_score: 50,
+        };
+
+        let failed_run = || -> Result<()> {
+            let mut writer = storage.open_parsed_writer(&output_path, 2)?;
+            writer.write_all(&[record(0), record(1), record(2)])?;
+            writer.write_batch((3..4).map(record))?;
+            anyhow::bail!("parser crashed")
+        };
+        assert!(failed_run().is_err());
+
+        let reader = SerializedFileReader::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 4);
+        assert_eq!(reader.metadata().num_row_groups(), 3);
+
+        let records = storage.read_parsed_data(&output_path, &ReadOptions::default()).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3].canonical_id, "id-3");
+        assert_eq!(records[3].text_length, 300);
+
+        std::fs::remove_file(output_path).ok();
+    }
 }
@@ -0,0 +1,462 @@
+use anyhow::{bail, Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// How long the synthetic server waits before answering
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyDistribution {
+    Fixed { ms: u64 },
+    Uniform { min_ms: u64, max_ms: u64 },
+    /// Long-tailed: most responses near the median, a few much slower
+    LogNormal { median_ms: u64, sigma: f64 },
+}
+
+impl LatencyDistribution {
+    fn sample(&self, rng: &mut StdRng) -> Duration {
+        let ms = match *self {
+            LatencyDistribution::Fixed { ms } => ms as f64,
+            LatencyDistribution::Uniform { min_ms, max_ms } => {
+                rng.gen_range(min_ms..=max_ms.max(min_ms)) as f64
+            }
+            LatencyDistribution::LogNormal { median_ms, sigma } => {
+                // Box-Muller for a standard normal sample
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                median_ms as f64 * (sigma * z).exp()
+            }
+        };
+        Duration::from_millis(ms.min(60_000.0) as u64)
+    }
+}
+
+impl fmt::Display for LatencyDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatencyDistribution::Fixed { ms } => write!(f, "fixed:{}", ms),
+            LatencyDistribution::Uniform { min_ms, max_ms } => {
+                write!(f, "uniform:{}-{}", min_ms, max_ms)
+            }
+            LatencyDistribution::LogNormal { median_ms, sigma } => {
+                write!(f, "lognormal:{},{}", median_ms, sigma)
+            }
+        }
+    }
+}
+
+impl FromStr for LatencyDistribution {
+    type Err = anyhow::Error;
+
+    /// "20" or "fixed:20", "uniform:5-50", "lognormal:20,0.8" (all in ms)
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, args) = s.split_once(':').unwrap_or(("fixed", s));
+        let number = |v: &str| {
+            v.trim()
+                .parse::<u64>()
+                .with_context(|| format!("Invalid latency '{}'", s))
+        };
+
+        match kind {
+            "fixed" => Ok(LatencyDistribution::Fixed { ms: number(args)? }),
+            "uniform" => {
+                let Some((min, max)) = args.split_once('-') else {
+                    bail!("Invalid latency '{}': expected uniform:MIN-MAX", s);
+                };
+                Ok(LatencyDistribution::Uniform {
+                    min_ms: number(min)?,
+                    max_ms: number(max)?,
+                })
+            }
+            "lognormal" => {
+                let Some((median, sigma)) = args.split_once(',') else {
+                    bail!("Invalid latency '{}': expected lognormal:MEDIAN,SIGMA", s);
+                };
+                let sigma = sigma
+                    .trim()
+                    .parse::<f64>()
+                    .with_context(|| format!("Invalid sigma in latency '{}'", s))?;
+                Ok(LatencyDistribution::LogNormal {
+                    median_ms: number(median)?,
+                    sigma,
+                })
+            }
+            other => bail!("Unknown latency distribution '{}' (fixed, uniform, lognormal)", other),
+        }
+    }
+}
+
+/// Response body size range in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodySize {
+    pub min_bytes: usize,
+    pub max_bytes: usize,
+}
+
+impl BodySize {
+    fn sample(&self, rng: &mut StdRng) -> usize {
+        rng.gen_range(self.min_bytes..=self.max_bytes.max(self.min_bytes))
+    }
+}
+
+impl fmt::Display for BodySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min_bytes == self.max_bytes {
+            write!(f, "{}", self.min_bytes / 1024)
+        } else {
+            write!(f, "{}-{}", self.min_bytes / 1024, self.max_bytes / 1024)
+        }
+    }
+}
+
+impl FromStr for BodySize {
+    type Err = anyhow::Error;
+
+    /// "4" (KB) or a range "1-64" (KB)
+    fn from_str(s: &str) -> Result<Self> {
+        let kb = |v: &str| {
+            v.trim()
+                .parse::<usize>()
+                .map(|kb| kb * 1024)
+                .with_context(|| format!("Invalid body size '{}'", s))
+        };
+        match s.split_once('-') {
+            Some((min, max)) => Ok(BodySize {
+                min_bytes: kb(min)?,
+                max_bytes: kb(max)?,
+            }),
+            None => {
+                let bytes = kb(s)?;
+                Ok(BodySize {
+                    min_bytes: bytes,
+                    max_bytes: bytes,
+                })
+            }
+        }
+    }
+}
+
+/// Behaviour of the synthetic server
+///
+/// Every decision for `/page/<id>` is drawn from an RNG seeded with
+/// `seed` and the id, so a given id behaves the same in every run
+/// regardless of request order or concurrency.
+#[derive(Debug, Clone)]
+pub struct SyntheticConfig {
+    pub latency: LatencyDistribution,
+    /// Fraction of pages answered with a 500 or 503
+    pub error_rate: f64,
+    pub body_size: BodySize,
+    /// Fraction of pages whose body is sent in small chunks with pauses
+    pub slow_drip_rate: f64,
+    pub drip_chunk_bytes: usize,
+    pub drip_interval_ms: u64,
+    /// Fraction of pages reached through a redirect chain
+    pub redirect_rate: f64,
+    pub redirect_hops: u32,
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            latency: LatencyDistribution::Fixed { ms: 0 },
+            error_rate: 0.0,
+            body_size: BodySize {
+                min_bytes: 4 * 1024,
+                max_bytes: 4 * 1024,
+            },
+            slow_drip_rate: 0.0,
+            drip_chunk_bytes: 512,
+            drip_interval_ms: 20,
+            redirect_rate: 0.0,
+            redirect_hops: 3,
+            seed: 42,
+        }
+    }
+}
+
+/// What the server does for one page
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    latency: Duration,
+    /// Status of the final response (after any redirects)
+    status: u16,
+    body_bytes: usize,
+    drip: bool,
+    redirects: u32,
+}
+
+impl SyntheticConfig {
+    fn plan(&self, id: u64) -> Plan {
+        // splitmix-style mixing so neighbouring ids get unrelated streams
+        let mut rng = StdRng::seed_from_u64(self.seed ^ id.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let latency = self.latency.sample(&mut rng);
+        let status = if rng.gen_bool(self.error_rate.clamp(0.0, 1.0)) {
+            if rng.gen_bool(0.5) {
+                500
+            } else {
+                503
+            }
+        } else {
+            200
+        };
+        let body_bytes = self.body_size.sample(&mut rng);
+        let drip = rng.gen_bool(self.slow_drip_rate.clamp(0.0, 1.0));
+        let redirects = if rng.gen_bool(self.redirect_rate.clamp(0.0, 1.0)) {
+            self.redirect_hops
+        } else {
+            0
+        };
+
+        Plan {
+            latency,
+            status,
+            body_bytes,
+            drip,
+            redirects,
+        }
+    }
+}
+
+/// Local HTTP/1.1 server producing configurable synthetic load
+///
+/// Serves `/page/<id>`; redirect chains go through `/page/<id>?hop=<n>`.
+/// Connections are kept alive so the numbers reflect the downloader, not
+/// connection setup. The server stops when dropped.
+pub struct SyntheticServer {
+    addr: SocketAddr,
+    requests: Arc<AtomicU64>,
+    task: JoinHandle<()>,
+}
+
+impl SyntheticServer {
+    /// Bind to an ephemeral port on 127.0.0.1 and start serving
+    pub async fn start(config: SyntheticConfig) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("Failed to bind synthetic server")?;
+        let addr = listener.local_addr()?;
+        let config = Arc::new(config);
+        let requests = Arc::new(AtomicU64::new(0));
+
+        let counter = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let config = Arc::clone(&config);
+                let counter = Arc::clone(&counter);
+                tokio::spawn(async move {
+                    let _ = handle_connection(socket, &config, &counter).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            requests,
+            task,
+        })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// URL of synthetic page `id`
+    pub fn url(&self, id: u64) -> String {
+        format!("{}/page/{}", self.base_url(), id)
+    }
+
+    /// Requests answered so far, redirect hops included
+    pub fn requests_served(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for SyntheticServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(
+    socket: TcpStream,
+    config: &SyntheticConfig,
+    requests: &AtomicU64,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await? == 0 {
+            return Ok(());
+        }
+
+        // Skip headers; GET requests carry no body
+        let mut close = false;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 {
+                return Ok(());
+            }
+            if header.trim().is_empty() {
+                break;
+            }
+            let header = header.to_ascii_lowercase();
+            close |= header.starts_with("connection:") && header.contains("close");
+        }
+
+        requests.fetch_add(1, Ordering::Relaxed);
+        let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+        respond(&mut writer, config, target).await?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+async fn respond<W>(writer: &mut W, config: &SyntheticConfig, target: &str) -> std::io::Result<()>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let Some(id) = path.strip_prefix("/page/").and_then(|id| id.parse::<u64>().ok()) else {
+        let head = "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nContent-Length: 9\r\n\r\n";
+        return writer.write_all(format!("{}not found", head).as_bytes()).await;
+    };
+    let hop: u32 = query
+        .strip_prefix("hop=")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+
+    let plan = config.plan(id);
+    tokio::time::sleep(plan.latency).await;
+
+    if hop < plan.redirects {
+        let response = format!(
+            "HTTP/1.1 302 Found\r\nLocation: /page/{}?hop={}\r\nContent-Length: 0\r\n\r\n",
+            id,
+            hop + 1
+        );
+        return writer.write_all(response.as_bytes()).await;
+    }
+
+    if plan.status != 200 {
+        let reason = if plan.status == 503 {
+            "Service Unavailable"
+        } else {
+            "Internal Server Error"
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+            plan.status,
+            reason,
+            reason.len(),
+            reason
+        );
+        return writer.write_all(response.as_bytes()).await;
+    }
+
+    let body = page_body(id, plan.body_bytes);
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+
+    if plan.drip {
+        let interval = Duration::from_millis(config.drip_interval_ms);
+        for chunk in body.as_bytes().chunks(config.drip_chunk_bytes.max(1)) {
+            writer.write_all(chunk).await?;
+            writer.flush().await?;
+            tokio::time::sleep(interval).await;
+        }
+        Ok(())
+    } else {
+        writer.write_all(body.as_bytes()).await
+    }
+}
+
+/// HTML page of exactly `size` bytes (or the bare skeleton if smaller)
+fn page_body(id: u64, size: usize) -> String {
+    const FILLER: &str = "<p>Synthetic product listing with prices and reviews.</p>\n";
+    let mut body = format!("<html><head><title>Page {}</title></head><body>\n", id);
+    let footer = "</body></html>\n";
+    while body.len() + FILLER.len() + footer.len() <= size {
+        body.push_str(FILLER);
+    }
+    let padding = size.saturating_sub(body.len() + footer.len());
+    body.push_str(&" ".repeat(padding));
+    body.push_str(footer);
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_specs_and_deterministic_plans() {
+        assert_eq!(
+            "uniform:5-50".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Uniform { min_ms: 5, max_ms: 50 }
+        );
+        assert_eq!(
+            "20".parse::<LatencyDistribution>().unwrap(),
+            LatencyDistribution::Fixed { ms: 20 }
+        );
+        assert!("lognormal:20".parse::<LatencyDistribution>().is_err());
+        assert_eq!("1-64".parse::<BodySize>().unwrap().max_bytes, 64 * 1024);
+
+        let config = SyntheticConfig {
+            latency: "lognormal:20,0.8".parse().unwrap(),
+            error_rate: 0.3,
+            redirect_rate: 0.3,
+            ..Default::default()
+        };
+        assert_eq!(config.plan(7), config.plan(7));
+
+        let plans: Vec<Plan> = (0..1000).map(|id| config.plan(id)).collect();
+        let errors = plans.iter().filter(|p| p.status != 200).count();
+        assert!((200..400).contains(&errors), "{} errors", errors);
+        assert_eq!(page_body(1, 4096).len(), 4096);
+    }
+
+    #[tokio::test]
+    async fn test_serves_redirect_chains_and_errors() {
+        let server = SyntheticServer::start(SyntheticConfig {
+            redirect_rate: 1.0,
+            redirect_hops: 2,
+            slow_drip_rate: 1.0,
+            drip_chunk_bytes: 1024,
+            drip_interval_ms: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let response = reqwest::get(server.url(3)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response.url().as_str().ends_with("/page/3?hop=2"));
+        assert_eq!(response.text().await.unwrap().len(), 4096);
+        assert_eq!(server.requests_served(), 3);
+
+        let failing = SyntheticServer::start(SyntheticConfig {
+            error_rate: 1.0,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let status = reqwest::get(failing.url(1)).await.unwrap().status();
+        assert!(status.is_server_error());
+    }
+}
@@ -40,7 +40,8 @@ for size in "${BENCHMARK_SIZES[@]}"; do
         echo -e "${YELLOW}Testing: ${size} URLs with ${workers} workers${NC}"
         
         start_time=$(date +%s.%N)
-        "$JARVIX_BIN" benchmark --urls "$size" --concurrent "$workers" || true
+        "$JARVIX_BIN" benchmark --urls "$size" --concurrent "$workers" \
+            --report "$PROJECT_ROOT/data/benchmarks/downloads_${size}_${workers}.json" || true
         end_time=$(date +%s.%N)
         
         duration=$(echo "$end_time - $start_time" | bc)